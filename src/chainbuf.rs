@@ -1,26 +1,53 @@
+use std::borrow::Cow;
 use std::cmp;
-use std::iter::repeat;
 use std::mem;
+use std::ops::{Bound, RangeBounds};
 use std::str;
 use std::str::Utf8Error;
 
 use std::rc::Rc;
-
-use std::collections::LinkedList;
+use std::sync::Arc;
+
+mod binary;
+mod cursor;
+mod edit;
+mod flavor;
+mod io;
+mod lines;
+mod multi;
+mod nodes;
+#[cfg(feature = "regex")]
+mod regex;
+mod search;
+mod split;
+
+pub use self::cursor::ChainCursor;
+pub use self::flavor::{Atomic, Flavor, Local};
+pub use self::lines::Lines;
+pub use self::multi::PatternSet;
+use self::nodes::NodeList;
+#[cfg(feature = "regex")]
+pub use self::regex::Regex;
+pub use self::search::{MatchIndices, Searcher};
+pub use self::split::Split;
 
 // Put these in other module and extend Chain
 #[cfg(feature = "nix")]
-use nix;
+use libc::off_t;
 #[cfg(feature = "nix")]
 use nix::fcntl as nf;
 #[cfg(feature = "nix")]
 use nix::sys::mman;
+#[cfg(all(feature = "nix", any(target_os = "android", target_os = "linux")))]
+use nix::sys::sendfile::sendfile;
 #[cfg(feature = "nix")]
 use nix::sys::stat;
 #[cfg(feature = "nix")]
-use nix::sys::uio::{writev, IoVec};
+use nix::sys::uio::{readv, writev, IoVec};
 #[cfg(feature = "nix")]
 use nix::unistd::close;
+#[cfg(all(feature = "nix", not(any(target_os = "android", target_os = "linux"))))]
+use nix::unistd::write;
 #[cfg(feature = "nix")]
 use nix::NixPath;
 #[cfg(feature = "nix")]
@@ -31,65 +58,6 @@ use std::slice;
 /// Minimum chb size
 pub static CHB_MIN_SIZE: usize = 32usize;
 
-/// Move at most n items from the front of src deque to thes back of
-/// dst deque.
-// XXX: if we had access to LinkedList impl, we could do this more effective
-fn move_n<T>(src: &mut LinkedList<T>, dst: &mut LinkedList<T>, n: usize) {
-    let mut nc = n;
-    while nc > 0 {
-        if let Some(el) = src.pop_front() {
-            dst.push_back(el);
-            nc -= 1;
-        } else {
-            break;
-        }
-    }
-}
-
-/// Finds subsequence of bytes in bytesequence. Returnt offset or None
-/// if nothing found.
-fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
-    unsafe {
-        let hs: &str = mem::transmute(haystack);
-        let ns: &str = mem::transmute(needle);
-        hs.find(ns)
-    }
-}
-
-fn find_overlap<U: Eq, T: Iterator<Item = U> + Clone>(large: T, short: T) -> usize {
-    let mut haystack_it = large.clone();
-    let mut needle_it = short.clone();
-    let mut matched = 0usize;
-    let mut current_needle = needle_it.clone();
-    loop {
-        if let Some(b) = current_needle.next() {
-            if let Some(h) = haystack_it.next() {
-                if b == h {
-                    // save position of iter for backtracking
-                    needle_it = current_needle.clone();
-                    current_needle = current_needle.clone();
-                    matched += 1;
-                } else {
-                    // match failed, if we have previous matches,
-                    // restore iter
-                    if matched > 0 {
-                        current_needle = needle_it.clone();
-                    }
-                    // restore haystack iter
-                    haystack_it = large.clone();
-                    matched = 0;
-                }
-            } else {
-                // haystack exhausted
-                haystack_it = large.clone();
-            }
-        } else {
-            break;
-        }
-    }
-    return matched;
-}
-
 /// Chained buffer of bytes.
 /// # Example
 /// ```
@@ -99,26 +67,50 @@ fn find_overlap<U: Eq, T: Iterator<Item = U> + Clone>(large: T, short: T) -> usi
 /// let some_bytes = chain.pullup(2);
 /// ```
 /// # Details of implementation
-/// Chainbuf consists of linked list of nodes, with `start` and `end`
-/// offsets and a reference counted pointer to DataHolder. DataHolders can be
+/// Chainbuf consists of deque of nodes, with `start` and `end`
+/// offsets and a reference counted pointer to DataHolder. Nodes are
+/// indexed by their offsets inside chain, so looking up node by offset
+/// takes O(log n). DataHolders can be
 /// shared across different chains, so for mutation new nodes and data holders
 /// are created (as in Copy-On-Write).
-pub struct Chain<'src> {
-    head: LinkedList<Node<'src>>,
+///
+/// DataHolders are shared through `Rc` by default. Chain with `Atomic`
+/// flavor (see `SyncChain`) uses `Arc` instead and can be sent to or shared
+/// between threads.
+pub struct Chain<'src, F: Flavor = Local> {
+    head: NodeList<'src, F>,
     length: usize,
+    // Bytes removed from the front of chain during its life, minus
+    // prepended ones (wrapping). Lets `Searcher` keep its position
+    // across drains.
+    consumed: usize,
+    // Number of changes of data other than appending to the end and
+    // removing from/prepending to the front (wrapping), and position of
+    // the latest one in `consumed` coordinates: data from there on could
+    // have been removed or changed. Lets `Searcher` rescan it.
+    edits: usize,
+    edited: usize,
 }
 
-struct NodeAtPosInfoMut<'a, 'src: 'a> {
-    node: &'a mut Node<'src>, // link to node
-    pos: usize,               // position of node in chain
-    offset: usize,            // offset inside node
-}
-
-struct NodeAtPosInfo<'a, 'src: 'a> {
-    node: &'a Node<'src>, // link to node
-    pos: usize,           // position of node in chain
-    offset: usize,        // offset inside node
-}
+/// Thread-safe chained buffer of bytes, which is `Send` and `Sync`.
+/// It has the same API as `Chain`, except that it's created with
+/// `SyncChain::default()`.
+/// # Example
+/// ```
+/// use chainbuf::SyncChain;
+/// use std::thread;
+/// let mut chain = SyncChain::default();
+/// chain.append_bytes("helloworld".as_bytes());
+/// let copy = chain.clone(); // shares data with chain
+/// let res = thread::spawn(move || {
+///     let mut chain = chain;
+///     chain.drain(5);
+///     chain
+/// }).join().unwrap();
+/// assert_eq!(res.peek(5).unwrap(), "world".as_bytes());
+/// assert_eq!(copy.peek(5).unwrap(), "hello".as_bytes());
+/// ```
+pub type SyncChain<'src> = Chain<'src, Atomic>;
 
 impl<'src> Chain<'src> {
     /// Creates new, empty chainbuf.
@@ -130,12 +122,76 @@ impl<'src> Chain<'src> {
     /// let mut chain = Chain::new();
     /// ```
     pub fn new() -> Chain<'src> {
-        Chain {
-            head: LinkedList::new(),
-            length: 0,
-        }
+        Chain::default()
+    }
+
+    /// Appends node, backed by user-supplied data holder, to the end of
+    /// chain. No data copy happens, all data of *holder* becomes part of
+    /// chain.
+    /// # Example
+    /// ```
+    /// use chainbuf::{Chain, DataHolder};
+    /// struct Hello;
+    /// impl DataHolder for Hello {
+    ///     fn get_data(&self, offset: usize, size: usize) -> &[u8] {
+    ///         &"hello".as_bytes()[offset..offset + size]
+    ///     }
+    ///     fn size(&self) -> usize {
+    ///         5
+    ///     }
+    /// }
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("world".as_bytes());
+    /// chain.append_holder(Hello);
+    /// assert_eq!(chain.pullup_all().unwrap(), "worldhello".as_bytes());
+    /// ```
+    pub fn append_holder<H: DataHolder + 'static>(&mut self, holder: H) {
+        let dh: Rc<dyn DataHolder> = Rc::new(holder);
+        self.add_node_tail(Node::with_immutable(dh));
+    }
+
+    /// Prepends node, backed by user-supplied data holder, to the
+    /// beginning of chain. See `append_holder`.
+    pub fn prepend_holder<H: DataHolder + 'static>(&mut self, holder: H) {
+        let dh: Rc<dyn DataHolder> = Rc::new(holder);
+        self.add_prepended(Node::with_immutable(dh));
     }
+}
 
+impl<'src> Chain<'src, Atomic> {
+    /// Appends node, backed by user-supplied data holder, to the end of
+    /// chain. No data copy happens, all data of *holder* becomes part of
+    /// chain. Holder should be `Send` and `Sync`.
+    /// # Example
+    /// ```
+    /// use chainbuf::{DataHolder, SyncChain};
+    /// struct Hello;
+    /// impl DataHolder for Hello {
+    ///     fn get_data(&self, offset: usize, size: usize) -> &[u8] {
+    ///         &"hello".as_bytes()[offset..offset + size]
+    ///     }
+    ///     fn size(&self) -> usize {
+    ///         5
+    ///     }
+    /// }
+    /// let mut chain = SyncChain::default();
+    /// chain.append_holder(Hello);
+    /// assert_eq!(chain.len(), 5);
+    /// ```
+    pub fn append_holder<H: DataHolder + Send + Sync + 'static>(&mut self, holder: H) {
+        let dh: Arc<dyn DataHolder + Send + Sync> = Arc::new(holder);
+        self.add_node_tail(Node::with_immutable(dh));
+    }
+
+    /// Prepends node, backed by user-supplied data holder, to the
+    /// beginning of chain. See `append_holder`.
+    pub fn prepend_holder<H: DataHolder + Send + Sync + 'static>(&mut self, holder: H) {
+        let dh: Arc<dyn DataHolder + Send + Sync> = Arc::new(holder);
+        self.add_prepended(Node::with_immutable(dh));
+    }
+}
+
+impl<'src, F: Flavor> Chain<'src, F> {
     /// Constructs new chainbuf from another chainbuf, destroying it.
     /// # Example
     /// ```
@@ -146,8 +202,8 @@ impl<'src> Chain<'src> {
     /// println!("{}", chain2.len()); // should print 10
     /// // println!("{}", chain1.len()); // should produce error `use of moved value`
     /// ```
-    pub fn from_foreign(src: Chain<'src>) -> Chain<'src> {
-        let mut ch = Chain::new();
+    pub fn from_foreign(src: Chain<'src, F>) -> Chain<'src, F> {
+        let mut ch = Chain::default();
         ch.concat(src);
         ch
     }
@@ -165,6 +221,20 @@ impl<'src> Chain<'src> {
         self.length
     }
 
+    /// Returns true if chainbuf holds no data.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// assert!(chain.is_empty());
+    /// chain.append_bytes("helloworld".as_bytes());
+    /// assert!(!chain.is_empty());
+    /// ```
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
     /// Copies bytes from a slice, and appends them to the end of chain,
     /// creating new node, if data holder in last node does not have enough
     /// room for data or shared across several chains.
@@ -221,8 +291,9 @@ impl<'src> Chain<'src> {
             .holder_mut()
             .unwrap()
             .fill_from(node.start - size, data);
-        node.start -= size;
+        self.head.retreat_front(size);
         self.length += size;
+        self.consumed = self.consumed.wrapping_sub(size);
     }
 
     /// Appends unowned *slice* to the chain without copy.
@@ -236,16 +307,68 @@ impl<'src> Chain<'src> {
     /// println!("{}", chain.len()); // should print 10
     /// ```
     pub fn append_slice(&mut self, data: &'src [u8]) {
-        let mut node = Node::with_data_holder(MemoryWrapper::new(data));
-        node.end = node.room();
-        self.add_node_tail(node);
+        self.add_node_tail(Node::with_borrowed(MemoryWrapper::new(data)));
+    }
+
+    /// Appends *data* to the chain without copy, taking ownership of it.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_vec(vec![1, 2, 3]);
+    /// assert_eq!(chain.pullup_all().unwrap(), &[1, 2, 3]);
+    /// ```
+    pub fn append_vec(&mut self, data: Vec<u8>) {
+        self.add_node_tail(Node::with_immutable(F::new_immutable(data)));
+    }
+
+    /// Appends boxed *data* to the chain without copy, taking ownership of
+    /// it.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_boxed(vec![1, 2, 3].into_boxed_slice());
+    /// assert_eq!(chain.len(), 3);
+    /// ```
+    pub fn append_boxed(&mut self, data: Box<[u8]>) {
+        self.add_node_tail(Node::with_immutable(F::new_immutable(data)));
+    }
+
+    /// Appends bytes of *data* to the chain without copy, taking ownership
+    /// of it.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_string(format!("hello{}", "world"));
+    /// assert_eq!(chain.pullup_all().unwrap(), "helloworld".as_bytes());
+    /// ```
+    pub fn append_string(&mut self, data: String) {
+        self.add_node_tail(Node::with_immutable(F::new_immutable(data)));
+    }
+
+    /// Appends static *data* to the chain without copy. Unlike
+    /// `append_slice` it does not bind chain lifetime to the lifetime
+    /// of data.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_static(b"HTTP/1.1 200 OK\r\n");
+    /// assert_eq!(chain.len(), 17);
+    /// ```
+    pub fn append_static(&mut self, data: &'static [u8]) {
+        self.add_node_tail(Node::with_immutable(F::new_immutable(data)));
     }
 
     /// Returns *size* bytes from the beginning of chain or None,
     /// if chain does not have enough data.
     /// # Note
     /// If data of requested size span multiple nodes, new node, containing
-    /// all requested data will be created instead.
+    /// all requested data will be created instead. That's why this method
+    /// needs mutable access to chain; use `peek` to look at data without
+    /// changing the chain.
     /// # Example
     /// ```
     /// use chainbuf::Chain;
@@ -256,37 +379,17 @@ impl<'src> Chain<'src> {
     /// assert_eq!(chain.pullup(2).unwrap(), "he".as_bytes()); // does not create new node
     /// assert_eq!(chain.pullup(25).unwrap(), "helloworldhelloworldhello".as_bytes()); // create new node
     /// ```
-    #[allow(mutable_transmutes)]
-    pub fn pullup(&self, size: usize) -> Option<&[u8]> {
-        // This method logically immutable, so it's nice to have &self
-        // in method signature.
-        // Idiomatic Rust way to implement such methods is to use RefCell.
-        // But we only need such feature in this method, and using RefCell
-        // to access nodelist (self.head) in all other methods looks like
-        // an overkill. And we'll also lose statically checked lifetimes for
-        // main field of Chain datastructure.
-        // It seems implementing this method with `unsafe` and `pullup` is
-        // far better than using RefCell everywhere.
+    pub fn pullup(&mut self, size: usize) -> Option<&[u8]> {
         if size == 0 || size > self.len() {
             return None;
         }
         // could not fail, because self.size() > 0 => has node
-        if self.head.front().unwrap().size() >= size {
-            let node = self.head.front().unwrap();
-            return Some(node.get_data_from_start(size));
-        }
-        let mut newn = Node::with_size(size);
-
-        let mut_self: &mut Chain;
-        unsafe {
-            mut_self = mem::transmute(self);
-        }
-        // XXX: we need this scope to be able to move newn inside our list
-        {
+        if self.head.front().unwrap().size() < size {
+            let mut newn = Node::with_size(size);
             let mut msize = size;
             while msize > 0 {
                 {
-                    let node = mut_self.head.front_mut().unwrap();
+                    let node = self.head.front().unwrap();
                     let csize = cmp::min(node.size(), msize);
                     // XXX: we need this scope only to beat borrow checker
                     {
@@ -298,20 +401,20 @@ impl<'src> Chain<'src> {
                     newn.end += csize;
 
                     if node.size() > msize {
-                        node.start += msize;
-                        mut_self.length -= msize;
+                        self.head.advance_front(msize);
+                        self.length -= msize;
                         break;
                     }
                 }
                 // infailable
-                let n = mut_self.head.pop_front().unwrap();
-                mut_self.length -= n.size();
+                let n = self.head.pop_front().unwrap();
+                self.length -= n.size();
                 msize -= n.size();
             }
+            self.add_node_head(newn);
         }
-        mut_self.add_node_head(newn);
-        // Now first node.size >= size, so we recurse
-        return mut_self.pullup(size);
+        // Now first node.size >= size
+        self.head.front().map(|n| n.get_data_from_start(size))
     }
 
     /// Returns slice of requested size starting from specified offset.
@@ -324,47 +427,74 @@ impl<'src> Chain<'src> {
     /// assert!(res.is_some());
     /// assert_eq!(res.unwrap(), "llow".as_bytes());
     /// ```
-    #[allow(mutable_transmutes)]
-    pub fn pullup_from(&self, offs: usize, size: usize) -> Option<&[u8]> {
-        if (offs >= self.len()) || (size == 0) {
+    pub fn pullup_from(&mut self, offs: usize, size: usize) -> Option<&[u8]> {
+        if (offs >= self.len()) || (size == 0) || (size > self.len() - offs) {
             return None;
         }
         // Fast path: check whether node at this position have all requested
         // data:
         // We've done sanity check, so can safely unwrap this:
-        let node_info = self.node_at_pos(offs).unwrap();
-        if size <= node_info.node.size() - node_info.offset {
-            return Some(node_info.node.get_data_from(node_info.offset, size));
+        let (idx, offset) = self.head.locate(offs).unwrap();
+        if size > self.head.get(idx).unwrap().size() - offset {
+            // If it's not the case, we need to rebuild our chain to provide
+            // contigious region of memory.
+            // Data is moved back and forth, so it's not consumed
+            let consumed = self.consumed;
+            let mut tmp = Chain::default();
+            tmp.move_from(self, offs);
+            // Run pullup to be sure, that we have dataholder that contains
+            // requested number of bytes in contigious memory
+            let _ = self.pullup(size);
+            tmp.move_all_from(self);
+            // Here we have emtpy self
+            self.concat(tmp);
+            self.consumed = consumed;
         }
-        // If it's not the case, we need to rebuild our chain to provide
-        // contigious region of memory.
-        // We need mutable reference to self for this, so once again use
-        // std::mem::transmute:
-        // XXX: see `pullup`.
-        let mut tmp = Chain::new();
-        {
-            let mut_self: &mut Chain;
-            unsafe {
-                mut_self = mem::transmute(self);
-            };
-            tmp.move_from(mut_self, offs);
+        // Now we can be sure that requested data fits inside one node
+        let (idx, offset) = self.head.locate(offs).unwrap();
+        self.head.get(idx).map(|n| n.get_data_from(offset, size))
+    }
+
+    /// Returns *size* bytes from the beginning of chain or None, if chain
+    /// does not have enough data. Unlike `pullup`, chain is not changed:
+    /// data is borrowed if it lies in one node, and copied otherwise.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// use std::borrow::Cow;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("hello".as_bytes());
+    /// chain.append_slice("world".as_bytes());
+    /// assert_eq!(chain.peek(100), None);
+    /// assert!(matches!(chain.peek(4), Some(Cow::Borrowed(b"hell"))));
+    /// assert_eq!(&chain.peek(7).unwrap()[..], "hellowo".as_bytes()); // copied
+    /// ```
+    pub fn peek(&self, size: usize) -> Option<Cow<'_, [u8]>> {
+        self.peek_from(0, size)
+    }
+
+    /// Returns slice of requested size starting from specified offset,
+    /// without changing the chain. See `peek`.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("hello".as_bytes());
+    /// chain.append_slice("world".as_bytes());
+    /// assert_eq!(&chain.peek_from(3, 4).unwrap()[..], "lowo".as_bytes());
+    /// ```
+    pub fn peek_from(&self, offs: usize, size: usize) -> Option<Cow<'_, [u8]>> {
+        if (offs >= self.len()) || (size == 0) || (size > self.len() - offs) {
+            return None;
         }
-        // Run pullup to be sure, that we have dataholder that contains
-        // requested number of bytes in contigious memory
-        let _ = self.pullup(size);
-        {
-            let mut_self: &mut Chain;
-            unsafe {
-                mut_self = mem::transmute(self);
-            };
-            tmp.move_all_from(mut_self);
-            // Here we have emtpy mut_self
-            mut_self.concat(tmp);
+        // We've done sanity check, so can safely unwrap this:
+        let (idx, offset) = self.head.locate(offs).unwrap();
+        let node = self.head.get(idx).unwrap();
+        if size <= node.size() - offset {
+            Some(Cow::Borrowed(node.get_data_from(offset, size)))
+        } else {
+            Some(Cow::Owned(self.copy_bytes_from(offs, size)))
         }
-
-        // Now we can be sure that requested data fits inside one node, so
-        // we recurse into itself to take Fast Path.
-        return self.pullup_from(offs, size);
     }
 
     /// Finds first occurence of *needle* inside chain and returns data
@@ -378,12 +508,10 @@ impl<'src> Chain<'src> {
     /// let res = chain.pullup_to("wor".as_bytes());
     /// assert_eq!(res.unwrap(), "hellowor".as_bytes());
     /// ```
-    pub fn pullup_to(&self, needle: &[u8]) -> Option<&[u8]> {
+    pub fn pullup_to(&mut self, needle: &[u8]) -> Option<&[u8]> {
         match self.find(needle) {
             Some(offset) => self.pullup(offset + needle.len()),
-            None => {
-                return None;
-            }
+            None => None,
         }
     }
 
@@ -396,7 +524,7 @@ impl<'src> Chain<'src> {
     /// let buf = chain.pullup_all();
     /// assert_eq!(buf.unwrap().len(), 10);
     /// ```
-    pub fn pullup_all(&self) -> Option<&[u8]> {
+    pub fn pullup_all(&mut self) -> Option<&[u8]> {
         let l = self.len();
         self.pullup(l)
     }
@@ -413,11 +541,8 @@ impl<'src> Chain<'src> {
     /// assert!(res.unwrap().is_ok());
     /// assert_eq!(res.unwrap().ok().unwrap(), "helloworld");
     /// ```
-    pub fn to_utf8_str(&self) -> Option<Result<&str, Utf8Error>> {
-        match self.pullup_all() {
-            Some(bytes) => Some(str::from_utf8(bytes)),
-            None => None,
-        }
+    pub fn to_utf8_str(&mut self) -> Option<Result<&str, Utf8Error>> {
+        self.pullup_all().map(str::from_utf8)
     }
 
     /// Consumes another chain and moves all data from it to itself.
@@ -431,7 +556,7 @@ impl<'src> Chain<'src> {
     /// chain1.concat(chain2);
     /// assert_eq!(chain1.pullup(10).unwrap(), "helloworld".as_bytes());
     /// ```
-    pub fn concat(&mut self, mut src: Chain<'src>) {
+    pub fn concat(&mut self, mut src: Chain<'src, F>) {
         self.length += src.length;
         self.head.append(&mut src.head);
         // No need to cleanup `src`, because it has moved and cannot be used
@@ -450,7 +575,8 @@ impl<'src> Chain<'src> {
     pub fn reset(&mut self) {
         // XXX: chb_drop; `drop` is the sole method of built-in Drop trait,
         // so use another name
-        self.head = LinkedList::new();
+        self.head = NodeList::new();
+        self.consumed = self.consumed.wrapping_add(self.length);
         self.length = 0;
     }
 
@@ -467,13 +593,56 @@ impl<'src> Chain<'src> {
     /// chain1.append(&chain2);
     /// assert_eq!(chain1.len(), chain2.len());
     /// ```
-    pub fn append(&mut self, src: &Chain<'src>) {
+    pub fn append(&mut self, src: &Chain<'src, F>) {
         // XXX: chb_copy
         for node in src.head.iter() {
             self.add_node_tail(node.clone());
         }
     }
 
+    /// Converts chain into one not bound to any borrowed data, so it
+    /// can outlive slices appended with `append_slice`.
+    /// # Note
+    /// Only data of unowned slices is copied, all other nodes keep
+    /// sharing their dataholders.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let owned: Chain<'static> = {
+    ///     let s = String::from("helloworld");
+    ///     let mut chain = Chain::new();
+    ///     chain.append_slice(s.as_bytes());
+    ///     chain.into_owned()
+    /// };
+    /// assert_eq!(owned.peek(10).unwrap(), "helloworld".as_bytes());
+    /// ```
+    pub fn into_owned(mut self) -> Chain<'static, F> {
+        let mut res = Chain::default();
+        while let Some(node) = self.head.pop_front() {
+            res.head.push_back(node.into_owned());
+        }
+        res.length = self.length;
+        res.consumed = self.consumed;
+        res.edits = self.edits;
+        res.edited = self.edited;
+        res
+    }
+
+    /// Returns copy of chain not bound to any borrowed data, leaving
+    /// chain itself intact. See `into_owned`.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let s = String::from("helloworld");
+    /// let mut chain = Chain::new();
+    /// chain.append_slice(s.as_bytes());
+    /// let owned: Chain<'static> = chain.to_owned_chain();
+    /// assert!(owned == chain);
+    /// ```
+    pub fn to_owned_chain(&self) -> Chain<'static, F> {
+        self.clone().into_owned()
+    }
+
     /// Moves at most size bytes from another chain and returns number of
     /// bytes moved.
     /// # Example
@@ -487,7 +656,7 @@ impl<'src> Chain<'src> {
     /// let moved_more = chain2.move_from(&mut chain1, 10);
     /// assert_eq!(moved_more, 7);
     /// ```
-    pub fn move_from<'a>(&mut self, src: &'a mut Chain<'src>, size: usize) -> usize {
+    pub fn move_from(&mut self, src: &mut Chain<'src, F>, size: usize) -> usize {
         if size == 0 {
             return 0;
         }
@@ -497,38 +666,21 @@ impl<'src> Chain<'src> {
             return sz;
         }
 
-        let move_nodes;
-        let mut newn = None;
-        // We've done checks, so we cannot have None here
-        {
-            let node_info = src.node_at_pos_mut(size).unwrap();
-            if node_info.offset != 0 {
-                // We requesting data in the middle of node, should split it then
-                let mut nn = node_info.node.clone();
-                nn.start += node_info.offset;
-                node_info.node.end = nn.start;
-                newn = Some(nn);
-                move_nodes = node_info.pos + 1;
-            } else {
-                // Requested data right on the border of nodes, can move all nodes
-                // before this one
-                move_nodes = node_info.pos;
-            }
-        }
-        move_n(&mut src.head, &mut self.head, move_nodes);
-        if newn.is_some() {
-            src.head.push_front(newn.unwrap());
-        }
+        // We've done checks, so size is inside of src
+        let mut moved = src.head.split_to(size);
+        self.head.append(&mut moved);
 
         self.length += size;
         src.length -= size;
+        src.consumed = src.consumed.wrapping_add(size);
 
-        return size;
+        size
     }
 
     /// Moves all data from sourche chain to itself.
     ///
-    /// This operation should compute in O(1).
+    /// This operation computes in O(1) if chain is empty and in O(n) of
+    /// source nodes otherwise.
     /// # Example
     /// ```
     /// use chainbuf::Chain;
@@ -539,13 +691,120 @@ impl<'src> Chain<'src> {
     /// assert_eq!(chain1.len(), 0);
     /// assert_eq!(chain2.len(), 10);
     /// ```
-    pub fn move_all_from(&mut self, src: &mut Chain<'src>) {
+    pub fn move_all_from(&mut self, src: &mut Chain<'src, F>) {
         self.length += src.length;
-        let mut sh = mem::replace(&mut src.head, LinkedList::new());
-        self.head.append(&mut sh);
+        self.head.append(&mut src.head);
+        src.consumed = src.consumed.wrapping_add(src.length);
         src.length = 0;
     }
 
+    /// Splits chain in two at offset *at*: returns new chain with first
+    /// *at* bytes and leaves the rest in this chain. If *at* falls inside
+    /// a node, both halves share its data holder, so no data copy happens.
+    /// If *at* is greater than size of chain, all data is returned.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("headerbody".as_bytes());
+    /// let mut header = chain.split_to(6);
+    /// assert_eq!(header.pullup_all().unwrap(), "header".as_bytes());
+    /// assert_eq!(chain.pullup_all().unwrap(), "body".as_bytes());
+    /// ```
+    pub fn split_to(&mut self, at: usize) -> Chain<'src, F> {
+        let mut res = Chain::default();
+        res.move_from(self, at);
+        res
+    }
+
+    /// Splits chain in two at offset *at*: returns new chain with data
+    /// after *at* and leaves first *at* bytes in this chain. As with
+    /// `split_to`, no data copy happens. If *at* is greater than size of
+    /// chain, empty chain is returned.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("headerbody".as_bytes());
+    /// let mut body = chain.split_off(6);
+    /// assert_eq!(chain.pullup_all().unwrap(), "header".as_bytes());
+    /// assert_eq!(body.pullup_all().unwrap(), "body".as_bytes());
+    /// ```
+    pub fn split_off(&mut self, at: usize) -> Chain<'src, F> {
+        let mut res = Chain::default();
+        if at < self.len() {
+            let front = self.head.split_to(at);
+            res.head = mem::replace(&mut self.head, front);
+            res.length = self.length - at;
+            self.length = at;
+            self.edited_at(at);
+        }
+        res
+    }
+
+    /// Returns new chain with data from *range*, leaving this chain
+    /// intact. Only nodes covering *range* are cloned, new chain shares
+    /// their data holders, so no data copy happens.
+    /// # Panics
+    /// Panics if *range* is out of bounds of chain or its start is greater
+    /// than its end, as slice indexing does.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("Length: 4\r\n\r\nbody".as_bytes());
+    /// let mut body = chain.slice(13..);
+    /// assert_eq!(body.pullup_all().unwrap(), "body".as_bytes());
+    /// assert_eq!(chain.len(), 17);
+    /// ```
+    pub fn slice<R: RangeBounds<usize>>(&self, range: R) -> Chain<'src, F> {
+        let (from, to) = self.range_offsets(range);
+        self.share_range(from, to)
+    }
+
+    /// Shortens chain to *len* bytes, dropping data from its end. Does
+    /// nothing if chain is not longer than *len*.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("hello".as_bytes());
+    /// chain.append_bytes("world".as_bytes());
+    /// chain.truncate(3);
+    /// assert_eq!(chain.pullup_all().unwrap(), "hel".as_bytes());
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        if self.length > len {
+            self.edited_at(len);
+        }
+        while self.length > len {
+            let excess = self.length - len;
+            // infailable: chain is not empty
+            let node = self.head.back_mut().unwrap();
+            if node.size() > excess {
+                node.end -= excess;
+                self.length = len;
+            } else {
+                self.length -= node.size();
+                self.head.pop_back();
+            }
+        }
+    }
+
+    /// Removes *size* bytes from the end of chain, see `truncate`.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("PING\r\n".as_bytes());
+    /// chain.drain_back(2);
+    /// assert_eq!(chain.pullup_all().unwrap(), "PING".as_bytes());
+    /// ```
+    pub fn drain_back(&mut self, size: usize) {
+        let len = self.length.saturating_sub(size);
+        self.truncate(len);
+    }
+
     /// Returns mutable slice of requested size that points to empty area in
     /// DataHolder. If requested size greater than available room in
     /// existing node, new node will be created.
@@ -617,15 +876,16 @@ impl<'src> Chain<'src> {
     /// assert_eq!(chain.len(), 16);
     /// ```
     pub fn drain(&mut self, size: usize) {
+        self.consumed = self.consumed.wrapping_add(cmp::min(size, self.length));
         let mut msize = size;
         while msize > 0 {
             {
-                let node = match self.head.front_mut() {
+                let node = match self.head.front() {
                     Some(nd) => nd,
                     None => break,
                 };
                 if node.size() > msize {
-                    node.start += msize;
+                    self.head.advance_front(msize);
                     self.length -= msize;
                     break;
                 }
@@ -649,41 +909,7 @@ impl<'src> Chain<'src> {
     /// assert_eq!(res.unwrap(), 4);
     /// ```
     pub fn find(&self, needle: &[u8]) -> Option<usize> {
-        let mut msum = 0;
-        let mut work_needle = needle;
-        for n in self.head.iter() {
-            // Try to find entire needle in one node
-            let node_data = n.get_data_from_start(n.size());
-            if let Some(offs) = find_bytes(node_data, work_needle) {
-                return Some(msum + offs);
-            } else {
-                // Entire needle wasn't found, maybe suffix of data is
-                // prefix of needle?
-                // Find number of overlaped bytes
-                let overlaped = if node_data.len() > work_needle.len() {
-                    // If node_data larger than needle we gonna search
-                    // from the back, looking whether some prefix of needle
-                    // equal to the suffix of node_data
-                    find_overlap(node_data.iter().rev(), work_needle.iter().rev())
-                } else {
-                    // Otherwase, we'srce searching for suffix of node_data
-                    // that equal to some prefix of the needle
-                    find_overlap(work_needle.iter(), node_data.iter())
-                };
-
-                if overlaped > 0 {
-                    // if we found something, move offset in needle
-                    work_needle = &needle[overlaped..];
-                } else {
-                    // we may have partial match before this point,
-                    // so we need to reset work_needle and start again
-                    work_needle = needle;
-                }
-            }
-            msum += n.size();
-        }
-
-        None
+        self.find_from(0, needle)
     }
 
     /// Copy size bytes from chain starting from specified offset.
@@ -701,21 +927,23 @@ impl<'src> Chain<'src> {
         let buf_size = cmp::min(size, self.len() - offs);
         let mut buf = Vec::with_capacity(buf_size);
         let mut msize = buf_size;
-        // Cannot fail: offs < self.len()
-        let node_info = self.node_at_pos(offs).unwrap();
-        let mut node: Option<&Node> = Some(node_info.node);
-        let mut moffs = node_info.offset;
-        let mut nodes_it = self.head.iter().skip(node_info.pos + 1);
-        while node.is_some() && (msize > 0) {
-            let tocopy = cmp::min(node.unwrap().size() - moffs, size);
-            let d = node.unwrap().get_data_from(moffs, tocopy);
-            buf.extend(d.iter().map(|x| x.clone()));
+        // Fails only when offs == self.len(): nothing to copy then
+        let (idx, mut moffs) = match self.head.locate(offs) {
+            Some(loc) => loc,
+            None => return buf,
+        };
+        for n in self.head.iter_from(idx) {
+            if msize == 0 {
+                break;
+            }
+            let tocopy = cmp::min(n.size() - moffs, msize);
+            let d = n.get_data_from(moffs, tocopy);
+            buf.extend_from_slice(d);
             msize -= d.len();
             moffs = 0;
-            node = nodes_it.next();
         }
 
-        return buf;
+        buf
     }
 
     /// Writes content of chain to specified file descriptor *fd*. Amount of
@@ -728,7 +956,9 @@ impl<'src> Chain<'src> {
     /// nodes.
     /// # Note
     /// It uses writev underneath, each node's content will go in corresponding
-    /// iovec struct in array of iovecs.
+    /// iovec struct in array of iovecs. Nodes added with `append_file` are
+    /// sent with sendfile(2) instead, without touching mapped pages, when
+    /// OS supports it. Writing stops at the first partial write.
     /// # Example
     /// ```
     /// use nix;
@@ -756,39 +986,165 @@ impl<'src> Chain<'src> {
         size: Option<usize>,
         nodes: Option<usize>,
     ) -> nix::Result<usize> {
-        let max_size = if size.is_some() {
-            size.unwrap()
-        } else {
-            self.len()
-        };
-        let max_nodes = if nodes.is_some() {
-            nodes.unwrap()
-        } else {
-            self.head.len()
-        };
-        // XXX: want to allocate this on stack, though
-        let res;
-        {
-            // Fchk you, borrowchecker!
-            let mut v = Vec::with_capacity(max_nodes);
-            let mut towrite = 0;
-
-            for n in self.head.iter().take(max_nodes) {
-                let ns = n.size();
-                v.push(IoVec::from_slice(n.get_data_from_start(ns)));
-                towrite += ns;
-                if towrite >= max_size {
+        let max_size = size.unwrap_or(self.len());
+        let max_nodes = nodes.unwrap_or(self.head.len());
+        let mut count = 0;
+        let mut towrite = 0;
+        for n in self.head.iter().take(max_nodes) {
+            count += 1;
+            towrite += n.size();
+            if towrite >= max_size {
+                break;
+            }
+        }
+
+        let mut written = 0;
+        let mut idx = 0;
+        let mut res = Ok(());
+        while idx < count {
+            let mut expected = 0;
+            let step = match self.head.get(idx).and_then(|n| n.file_region()) {
+                Some(region) => {
+                    // infailable: region found for this node above
+                    let node = self.head.get(idx).unwrap();
+                    expected = node.size();
+                    idx += 1;
+                    send_region(fd, region, node.get_data_from_start(expected))
+                }
+                None => {
+                    // XXX: want to allocate this on stack, though
+                    let mut v = Vec::with_capacity(count - idx);
+                    for n in self.head.iter_from(idx).take(count - idx) {
+                        if n.file_region().is_some() {
+                            break;
+                        }
+                        let ns = n.size();
+                        v.push(IoVec::from_slice(n.get_data_from_start(ns)));
+                        expected += ns;
+                    }
+                    idx += v.len();
+                    writev(fd, &v[..])
+                }
+            };
+            match step {
+                Ok(n) => {
+                    written += n;
+                    if n < expected {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    // Report bytes that already went out, next call
+                    // will get error again
+                    if written == 0 {
+                        res = Err(e);
+                    }
                     break;
                 }
             }
+        }
 
-            res = writev(fd, &v[..]);
+        self.drain(written);
+        res.map(|_| written)
+    }
+
+    /// Reads at most *max* bytes from file descriptor *fd* to the end of
+    /// chain and returns number of bytes read.
+    /// # Note
+    /// It uses readv underneath: data goes to the room left in the last
+    /// node (if chain is its only owner) and to the new node, that is
+    /// allocated for the rest of *max*. Only bytes actually read are
+    /// added to chain, new node is dropped if nothing got into it.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// use nix::unistd::{close, pipe, write};
+    /// let (reader, writer) = pipe().unwrap();
+    /// write(writer, "HelloWorld".as_bytes()).unwrap();
+    /// close(writer).unwrap();
+    /// let mut chain = Chain::new();
+    /// let read = chain.read_from_fd(reader, 1024).unwrap();
+    /// assert_eq!(read, 10);
+    /// assert_eq!(chain.pullup_all().unwrap(), "HelloWorld".as_bytes());
+    /// close(reader).unwrap();
+    /// ```
+    #[cfg(feature = "nix")]
+    pub fn read_from_fd(&mut self, fd: RawFd, max: usize) -> nix::Result<usize> {
+        if max == 0 {
+            return Ok(0);
+        }
+        let mut fresh = None;
+        let tail_room;
+        let res;
+        {
+            let mut v = Vec::with_capacity(2);
+            let tail = self.tail_room(max);
+            tail_room = tail.as_ref().map_or(0, |t| t.len());
+            if tail_room < max {
+                fresh = Some(Node::with_size(max - tail_room));
+            }
+            if let Some(t) = tail {
+                v.push(IoVec::from_mut_slice(t));
+            }
+            if let Some(node) = fresh.as_mut() {
+                let size = node.room();
+                let dh = node.dh.holder_mut().unwrap();
+                v.push(IoVec::from_mut_slice(dh.get_data_mut(0, size)));
+            }
+            res = readv(fd, &mut v[..]);
         }
 
-        if res.is_ok() {
-            self.drain(res.ok().unwrap());
+        if let Ok(read) = res {
+            let to_tail = cmp::min(read, tail_room);
+            if to_tail > 0 {
+                self.written(to_tail);
+            }
+            if read > to_tail {
+                // infailable: fresh node covers everything past tail room
+                let mut node = fresh.unwrap();
+                node.end = read - to_tail;
+                self.add_node_tail(node);
+            }
+        }
+        res
+    }
+
+    /// Reads data with *read* into the room left in the last node, or into
+    /// new node of *max* bytes if there is no room, and returns number of
+    /// bytes read. New node is only added to chain if something was read.
+    pub(crate) fn read_into_tail<E, R>(&mut self, max: usize, read: R) -> Result<usize, E>
+    where
+        R: FnOnce(&mut [u8]) -> Result<usize, E>,
+    {
+        if max == 0 {
+            return Ok(0);
+        }
+        if let Some(tail) = self.tail_room(max) {
+            let n = read(tail)?;
+            self.written(n);
+            return Ok(n);
+        }
+        let mut node = Node::with_size(max);
+        // we just created new data holder, so we have unique ownership
+        let n = read(node.dh.holder_mut().unwrap().get_data_mut(0, max))?;
+        if n > 0 {
+            node.end = n;
+            self.add_node_tail(node);
+        }
+        Ok(n)
+    }
+
+    /// Returns room left in the last node, up to *max* bytes, if chain is
+    /// the only owner of its data holder, or None if there is no room.
+    /// Data written there is added to chain with `written`.
+    fn tail_room(&mut self, max: usize) -> Option<&mut [u8]> {
+        let node = self.head.back_mut()?;
+        let size = cmp::min(node.room(), max);
+        if node.holds_readonly() || size == 0 {
+            return None;
         }
-        return res;
+        let end = node.end;
+        node.dh.holder_mut().map(|dh| dh.get_data_mut(end, size))
     }
 
     /// Appends file on *path* to chainbuf by memory mapping it.
@@ -809,96 +1165,91 @@ impl<'src> Chain<'src> {
         let fdst = stat::fstat(fd)?;
         // XXX: fstat's st_size is signed, but in practice it shouldn't be
         let size: usize = fdst.st_size as usize;
-        let dh = MmappedFile::new(fd, size)?;
-        let mut node = Node::with_data_holder(dh);
-        node.end = node.room();
-        self.add_node_tail(node);
-        return Ok(());
-    }
-
-    // XXX: private
-    // XXX: horrible code duplication with only difference in `mut` :(
-    fn node_at_pos_mut<'a>(&'a mut self, pos: usize) -> Option<NodeAtPosInfoMut<'a, 'src>> {
-        if (pos << 1) > self.len() {
-            // Find from tail
-            let mut toff = self.len(); // tail offset
-            for (i, n) in self.head.iter_mut().rev().enumerate() {
-                let nsize = n.size();
-                if (toff - pos) <= nsize {
-                    return Some(NodeAtPosInfoMut {
-                        node: n,
-                        pos: i,
-                        offset: (nsize - (toff - pos)),
-                    });
-                }
-                toff -= nsize;
-            }
-        } else {
-            // Find from begining
-            let mut hoff = 0; // head offset
-            for (i, n) in self.head.iter_mut().enumerate() {
-                let nsize = n.size();
-                if (pos - hoff) < nsize {
-                    return Some(NodeAtPosInfoMut {
-                        node: n,
-                        pos: i,
-                        offset: pos - hoff,
-                    });
-                }
-                hoff += nsize;
-            }
-        }
-        None
-    }
-
-    fn node_at_pos<'a>(&'a self, pos: usize) -> Option<NodeAtPosInfo<'a, 'src>> {
-        if (pos << 1) > self.len() {
-            // Find from tail
-            let mut toff = self.len(); // tail offset
-            for (i, n) in self.head.iter().rev().enumerate() {
-                let nsize = n.size();
-                if (toff - pos) <= nsize {
-                    return Some(NodeAtPosInfo {
-                        node: n,
-                        pos: i,
-                        offset: (nsize - (toff - pos)),
-                    });
-                }
-                toff -= nsize;
-            }
-        } else {
-            // Find from begining
-            let mut hoff = 0; // head offset
-            for (i, n) in self.head.iter().enumerate() {
-                let nsize = n.size();
-                if (pos - hoff) < nsize {
-                    return Some(NodeAtPosInfo {
-                        node: n,
-                        pos: i,
-                        offset: pos - hoff,
-                    });
-                }
-                hoff += nsize;
-            }
-        }
-        None
+        let mf = MmappedFile::new(fd, 0, size)?;
+        self.add_node_tail(Node::with_file(mf));
+        Ok(())
     }
 
-    fn add_node_tail(&mut self, node: Node<'src>) {
+    /// Converts *range* to offsets of its first byte and byte after its
+    /// end, panicking if it's out of bounds of chain.
+    fn range_offsets<R: RangeBounds<usize>>(&self, range: R) -> (usize, usize) {
+        let from = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let to = match range.end_bound() {
+            Bound::Included(&n) => n + 1,
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.len(),
+        };
+        assert!(
+            from <= to,
+            "slice index starts at {} but ends at {}",
+            from,
+            to
+        );
+        assert!(
+            to <= self.len(),
+            "range end index {} out of range for chain of length {}",
+            to,
+            self.len()
+        );
+        (from, to)
+    }
+
+    fn add_node_tail(&mut self, node: Node<'src, F>) {
         self.length += node.size();
         self.head.push_back(node);
     }
 
-    fn add_node_head(&mut self, node: Node<'src>) {
+    fn add_node_head(&mut self, node: Node<'src, F>) {
         self.length += node.size();
         self.head.push_front(node);
     }
+
+    /// Records change of data from offset *offs* on, see `Searcher`.
+    fn edited_at(&mut self, offs: usize) {
+        self.edits = self.edits.wrapping_add(1);
+        self.edited = self.consumed.wrapping_add(offs);
+    }
+
+    /// Adds node with new data to the head of chain.
+    fn add_prepended(&mut self, node: Node<'src, F>) {
+        self.consumed = self.consumed.wrapping_sub(node.size());
+        self.add_node_head(node);
+    }
+}
+
+impl<'src, F: Flavor> Default for Chain<'src, F> {
+    fn default() -> Chain<'src, F> {
+        Chain {
+            head: NodeList::new(),
+            length: 0,
+            consumed: 0,
+            edits: 0,
+            edited: 0,
+        }
+    }
+}
+
+/// Cloned chain shares all data holders with original one, no data
+/// copy happens (see `append`).
+impl<'src, F: Flavor> Clone for Chain<'src, F> {
+    fn clone(&self) -> Chain<'src, F> {
+        let mut ch = Chain::default();
+        ch.append(self);
+        ch.consumed = self.consumed;
+        ch.edits = self.edits;
+        ch.edited = self.edited;
+        ch
+    }
 }
 
 /// Chains are considered equal iff they have same content inside.
 /// Memory layout is not important.
-impl<'src> PartialEq for Chain<'src> {
-    fn eq(&self, other: &Chain) -> bool {
+impl<'src, F: Flavor> PartialEq for Chain<'src, F> {
+    fn eq(&self, other: &Chain<'src, F>) -> bool {
         if self.len() != other.len() {
             return false;
         }
@@ -933,34 +1284,81 @@ impl<'src> PartialEq for Chain<'src> {
         // We have size check before the loop and the loop simultaneously
         // consumes bytes from both chains, so here we have identical chains
         // (with possibly different layouts).
-        return true;
+        true
     }
 }
 
 /// Node of chain buffer.
 /// Owned by Chain.
-struct Node<'src> {
-    dh: DataHolder<'src>,
+struct Node<'src, F: Flavor> {
+    dh: Holder<'src, F>,
     start: usize,
     end: usize,
 }
 
-impl<'src> Node<'src> {
+impl<'src, F: Flavor> Node<'src, F> {
     #[inline]
     /// Creates new node with MemoryBuffer of *size* bytes as dataholder
-    fn with_size(size: usize) -> Node<'src> {
-        Node::with_data_holder(MemoryBuffer::new(size))
+    fn with_size(size: usize) -> Node<'src, F> {
+        Node::with_data_holder(Holder::Mutable(F::new_mutable(MemoryBuffer::new(size))))
     }
 
     #[inline]
-    fn with_data_holder(dh: DataHolder<'src>) -> Node<'src> {
+    fn with_data_holder(dh: Holder<'src, F>) -> Node<'src, F> {
         Node {
-            dh: dh,
+            dh,
             start: 0,
             end: 0,
         }
     }
 
+    #[inline]
+    /// Creates new node spanning all data of immutable dataholder
+    fn with_immutable(dh: F::Immutable) -> Node<'src, F> {
+        Node::spanning(Holder::Immutable(dh))
+    }
+
+    #[inline]
+    /// Creates new node spanning all data of unowned slice
+    fn with_borrowed(mw: MemoryWrapper<'src>) -> Node<'src, F> {
+        Node::spanning(Holder::Borrowed(mw))
+    }
+
+    #[inline]
+    /// Creates new node spanning whole memory mapped file
+    #[cfg(feature = "nix")]
+    fn with_file(mf: MmappedFile) -> Node<'src, F> {
+        let region = mf.region();
+        Node::spanning(Holder::File(F::new_immutable(mf), region))
+    }
+
+    #[inline]
+    fn spanning(dh: Holder<'src, F>) -> Node<'src, F> {
+        let mut node = Node::with_data_holder(dh);
+        node.end = node.room();
+        node
+    }
+
+    /// Converts node into one that doesn't borrow anything. Data of
+    /// borrowed node is copied, other dataholders are kept shared.
+    fn into_owned(self) -> Node<'static, F> {
+        let dh = match self.dh {
+            Holder::Mutable(rcbdh) => Holder::Mutable(rcbdh),
+            Holder::Immutable(rcbdh) => Holder::Immutable(rcbdh),
+            #[cfg(feature = "nix")]
+            Holder::File(rcbdh, region) => Holder::File(rcbdh, region),
+            Holder::Borrowed(mw) => {
+                let data = mw.get_data(self.start, self.end - self.start);
+                return Node::with_immutable(F::new_immutable(data.to_vec()));
+            }
+        };
+        Node {
+            dh,
+            start: self.start,
+            end: self.end,
+        }
+    }
+
     #[inline]
     fn size(&self) -> usize {
         self.end - self.start
@@ -985,11 +1383,25 @@ impl<'src> Node<'src> {
     fn get_data_from(&self, offs: usize, size: usize) -> &[u8] {
         self.dh.holder().get_data(self.start + offs, size)
     }
+
+    /// Returns descriptor and offset inside file of node data, if node
+    /// is backed by file.
+    #[inline]
+    #[cfg(feature = "nix")]
+    fn file_region(&self) -> Option<FileRegion> {
+        match &self.dh {
+            Holder::File(_, region) => Some(FileRegion {
+                fd: region.fd,
+                offset: region.offset + self.start as off_t,
+            }),
+            _ => None,
+        }
+    }
 }
 
-impl<'src> Clone for Node<'src> {
+impl<'src, F: Flavor> Clone for Node<'src, F> {
     #[inline]
-    fn clone(&self) -> Node<'src> {
+    fn clone(&self) -> Node<'src, F> {
         let mut newn = Node::with_data_holder(self.dh.clone());
         newn.start = self.start;
         newn.end = self.end;
@@ -997,16 +1409,42 @@ impl<'src> Clone for Node<'src> {
     }
 }
 
-/// Trait representing immutable data holders: mmap, mem wrapper, enc.
-trait ImmutableDataHolder {
+/// Read-only storage of bytes that chain nodes can refer to.
+///
+/// Implement it to back chain nodes with your own storage (DMA buffers,
+/// pooled slabs, foreign C buffers) and attach it with
+/// `Chain::append_holder` or `Chain::prepend_holder`. Data holder is shared
+/// by all nodes (possibly, in different chains) that refer to it and is
+/// dropped when the last of them goes away.
+/// Data returned by holder should not change while it's attached to chain.
+/// # Example
+/// ```
+/// use chainbuf::{Chain, DataHolder};
+/// struct Slab {
+///     buf: Box<[u8]>,
+/// }
+/// impl DataHolder for Slab {
+///     fn get_data(&self, offset: usize, size: usize) -> &[u8] {
+///         &self.buf[offset..offset + size]
+///     }
+///     fn size(&self) -> usize {
+///         self.buf.len()
+///     }
+/// }
+/// let mut chain = Chain::new();
+/// chain.append_holder(Slab { buf: Box::new(*b"helloworld") });
+/// assert_eq!(chain.len(), 10);
+/// ```
+pub trait DataHolder {
     /// Returns *size* bytes from dataholder starting from *offset*.
+    /// Chain never requests data beyond `size()`.
     fn get_data(&self, offset: usize, size: usize) -> &[u8];
     /// Return size of dataholder.
     fn size(&self) -> usize;
 }
 
 /// Trait representing _possible_ mutable data holders.
-trait MutableDataHolder: ImmutableDataHolder {
+pub trait MutableDataHolder: DataHolder {
     /// Fills buffer from offset *dst_offs* by copying data from supplied
     /// buffer *src*.
     fn fill_from(&mut self, dst_offs: usize, src: &[u8]);
@@ -1015,58 +1453,67 @@ trait MutableDataHolder: ImmutableDataHolder {
     /// starting from *offset*.
     fn get_data_mut(&mut self, offset: usize, size: usize) -> &mut [u8];
 
-    /// Upcast &MutableDataHolder to &ImmutableDataHolder
+    /// Upcast &MutableDataHolder to &DataHolder
     // XXX: rust doesn't support upcasting to supertrait yet
     // https://github.com/rust-lang/rust/issues/5665
-    fn as_immut<'a>(&'a self) -> &'a dyn ImmutableDataHolder;
+    fn as_immut(&self) -> &dyn DataHolder;
 }
 
 /// DataHolder type.
-enum DataHolder<'src> {
-    Mutable(Rc<dyn MutableDataHolder + 'src>),
-    Immutable(Rc<dyn ImmutableDataHolder + 'src>),
+enum Holder<'src, F: Flavor> {
+    Mutable(F::Mutable),
+    Immutable(F::Immutable),
+    // Unowned slice, the only holder bound to *'src*
+    Borrowed(MemoryWrapper<'src>),
+    // Memory mapped file, which also can be sent directly by descriptor
+    #[cfg(feature = "nix")]
+    File(F::Immutable, FileRegion),
 }
 
-impl<'src> DataHolder<'src> {
+impl<'src, F: Flavor> Holder<'src, F> {
     #[inline]
     fn holder_mut(&mut self) -> Option<&mut dyn MutableDataHolder> {
         match self {
-            &mut DataHolder::Mutable(ref mut rcbdh) => {
-                if let Some(bdh) = Rc::get_mut(rcbdh) {
-                    Some(&mut *bdh)
-                } else {
-                    None
-                }
-            }
-            &mut DataHolder::Immutable(_) => None,
+            Holder::Mutable(rcbdh) => F::mutable_mut(rcbdh),
+            Holder::Immutable(_) | Holder::Borrowed(_) => None,
+            #[cfg(feature = "nix")]
+            Holder::File(..) => None,
         }
     }
 
     #[inline]
-    fn holder(&self) -> &dyn ImmutableDataHolder {
+    fn holder(&self) -> &dyn DataHolder {
         match self {
-            &DataHolder::Mutable(ref mbdh) => (&**mbdh).as_immut(),
-            &DataHolder::Immutable(ref imbdh) => &**imbdh,
+            Holder::Mutable(mbdh) => F::mutable(mbdh).as_immut(),
+            Holder::Immutable(imbdh) => F::immutable(imbdh),
+            Holder::Borrowed(mw) => mw,
+            #[cfg(feature = "nix")]
+            Holder::File(imbdh, _) => F::immutable(imbdh),
         }
     }
 
     #[inline]
     fn is_readonly(&self) -> bool {
+        // Shared mutable holders are read-only as well: writing to them
+        // would change data in other chains
         match self {
-            &DataHolder::Mutable(ref rcbdh) => {
-                Rc::weak_count(rcbdh) == 0 && Rc::strong_count(rcbdh) == 1
-            }
-            &DataHolder::Immutable(_) => true,
+            Holder::Mutable(rcbdh) => !F::is_unique(rcbdh),
+            Holder::Immutable(_) | Holder::Borrowed(_) => true,
+            #[cfg(feature = "nix")]
+            Holder::File(..) => true,
         }
     }
 }
 
-impl<'src> Clone for DataHolder<'src> {
+impl<'src, F: Flavor> Clone for Holder<'src, F> {
     #[inline]
-    fn clone(&self) -> DataHolder<'src> {
+    fn clone(&self) -> Holder<'src, F> {
         match self {
-            &DataHolder::Mutable(ref rcbdh) => DataHolder::Mutable(rcbdh.clone()),
-            &DataHolder::Immutable(ref rcbdh) => DataHolder::Immutable(rcbdh.clone()),
+            Holder::Mutable(rcbdh) => Holder::Mutable(rcbdh.clone()),
+            Holder::Immutable(rcbdh) => Holder::Immutable(rcbdh.clone()),
+            Holder::Borrowed(mw) => Holder::Borrowed(*mw),
+            #[cfg(feature = "nix")]
+            Holder::File(rcbdh, region) => Holder::File(rcbdh.clone(), *region),
         }
     }
 }
@@ -1080,15 +1527,15 @@ struct MemoryBuffer {
 
 impl MemoryBuffer {
     #[inline]
-    fn new<'src>(size: usize) -> DataHolder<'src> {
-        DataHolder::Mutable(Rc::new(MemoryBuffer {
-            size: size,
-            data: repeat(0).take(size).collect(),
-        }))
+    fn new(size: usize) -> MemoryBuffer {
+        MemoryBuffer {
+            size,
+            data: vec![0; size],
+        }
     }
 }
 
-impl ImmutableDataHolder for MemoryBuffer {
+impl DataHolder for MemoryBuffer {
     #[inline]
     fn get_data(&self, offset: usize, size: usize) -> &[u8] {
         &self.data[offset..(offset + size)]
@@ -1116,23 +1563,24 @@ impl MutableDataHolder for MemoryBuffer {
         &mut self.data[offset..offset + size]
     }
 
-    fn as_immut<'a>(&'a self) -> &'a dyn ImmutableDataHolder {
+    fn as_immut(&self) -> &dyn DataHolder {
         self
     }
 }
 
 /// Dataholder as wrapper over some unowned slice.
+#[derive(Clone, Copy)]
 struct MemoryWrapper<'a> {
     data: &'a [u8],
 }
 
 impl<'a> MemoryWrapper<'a> {
-    fn new<'src>(data: &'src [u8]) -> DataHolder<'src> {
-        DataHolder::Immutable(Rc::new(MemoryWrapper { data: data }))
+    fn new(data: &'a [u8]) -> MemoryWrapper<'a> {
+        MemoryWrapper { data }
     }
 }
 
-impl<'a> ImmutableDataHolder for MemoryWrapper<'a> {
+impl<'a> DataHolder for MemoryWrapper<'a> {
     #[inline]
     fn get_data(&self, offset: usize, size: usize) -> &[u8] {
         &self.data[offset..offset + size]
@@ -1144,36 +1592,131 @@ impl<'a> ImmutableDataHolder for MemoryWrapper<'a> {
     }
 }
 
+/// Owned vector as dataholder, see `Chain::append_vec`.
+impl DataHolder for Vec<u8> {
+    #[inline]
+    fn get_data(&self, offset: usize, size: usize) -> &[u8] {
+        &self[offset..offset + size]
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Owned boxed slice as dataholder, see `Chain::append_boxed`.
+impl DataHolder for Box<[u8]> {
+    #[inline]
+    fn get_data(&self, offset: usize, size: usize) -> &[u8] {
+        &self[offset..offset + size]
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Owned string as dataholder, see `Chain::append_string`.
+impl DataHolder for String {
+    #[inline]
+    fn get_data(&self, offset: usize, size: usize) -> &[u8] {
+        &self.as_bytes()[offset..offset + size]
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Static data as dataholder, see `Chain::append_static`.
+impl DataHolder for &'static [u8] {
+    #[inline]
+    fn get_data(&self, offset: usize, size: usize) -> &[u8] {
+        &self[offset..offset + size]
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        self.len()
+    }
+}
+
 /// Dataholder as wrapper over mmaped file.
 #[cfg(feature = "nix")]
 struct MmappedFile {
     size: usize,
     fd: RawFd,
+    offset: off_t,
     addr: *const u8,
 }
 
+/// Part of file starting at *offset*.
+#[cfg(feature = "nix")]
+#[derive(Clone, Copy)]
+struct FileRegion {
+    fd: RawFd,
+    offset: off_t,
+}
+
+#[cfg(feature = "nix")]
 impl MmappedFile {
-    fn new<'src>(fd: RawFd, size: usize) -> nix::Result<DataHolder<'src>> {
+    /// Maps *size* bytes of file *fd* starting from *offset*, which
+    /// should be multiple of page size. Takes ownership of *fd*.
+    fn new(fd: RawFd, offset: off_t, size: usize) -> nix::Result<MmappedFile> {
         let addr = unsafe {
             mman::mmap(
-                0 as *mut std::ffi::c_void,
+                std::ptr::null_mut(),
                 size,
                 mman::ProtFlags::PROT_READ,
                 mman::MapFlags::MAP_SHARED,
                 fd,
-                0,
+                offset,
             )?
         };
 
-        let dh = DataHolder::Immutable(Rc::new(MmappedFile {
-            size: size,
-            fd: fd,
+        Ok(MmappedFile {
+            size,
+            fd,
+            offset,
             addr: addr as *const u8,
-        }));
-        Ok(dh)
+        })
+    }
+
+    #[inline]
+    fn region(&self) -> FileRegion {
+        FileRegion {
+            fd: self.fd,
+            offset: self.offset,
+        }
     }
 }
 
+// Mapping is read-only and is owned by data holder, so it can be accessed
+// from any thread.
+#[cfg(feature = "nix")]
+unsafe impl Send for MmappedFile {}
+#[cfg(feature = "nix")]
+unsafe impl Sync for MmappedFile {}
+
+/// Writes *data* of file *region* to *fd* without copying it through
+/// userspace.
+#[cfg(all(feature = "nix", any(target_os = "android", target_os = "linux")))]
+fn send_region(fd: RawFd, region: FileRegion, data: &[u8]) -> nix::Result<usize> {
+    let mut offset = region.offset;
+    sendfile(fd, region.fd, Some(&mut offset), data.len())
+}
+
+/// Writes *data* of file *region* to *fd*; there is no sendfile(2) with
+/// the same semantics on this platform, so mapped data is written.
+#[cfg(all(feature = "nix", not(any(target_os = "android", target_os = "linux"))))]
+fn send_region(fd: RawFd, _region: FileRegion, data: &[u8]) -> nix::Result<usize> {
+    write(fd, data)
+}
+
+#[cfg(feature = "nix")]
 impl Drop for MmappedFile {
     fn drop(&mut self) {
         let munmap_res = unsafe { mman::munmap(self.addr as *mut std::ffi::c_void, self.size) };
@@ -1182,10 +1725,11 @@ impl Drop for MmappedFile {
     }
 }
 
-impl ImmutableDataHolder for MmappedFile {
+#[cfg(feature = "nix")]
+impl DataHolder for MmappedFile {
     #[inline]
     fn get_data(&self, offset: usize, size: usize) -> &[u8] {
-        unsafe { slice::from_raw_parts(self.addr.offset(offset as isize), size) }
+        unsafe { slice::from_raw_parts(self.addr.add(offset), size) }
     }
 
     #[inline]
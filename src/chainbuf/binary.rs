@@ -0,0 +1,186 @@
+use std::cmp;
+use std::io;
+use std::mem;
+
+use super::{Chain, Flavor};
+
+// Longest LEB128 encoding of u64
+const MAX_VARINT_LEN: usize = 10;
+
+// Generates `peek_*`, `get_*` and `put_*` methods for numeric type. Values
+// are assembled in array on the stack, so they can straddle nodes.
+macro_rules! binary_accessors {
+    ($($ty:ident, $desc:expr, $peek:ident, $get:ident, $put:ident, $from:ident, $to:ident;)*) => {
+        $(
+            #[doc = concat!("Returns ", $desc, " from the beginning of chain, or None if ")]
+            #[doc = "chain does not have enough data. Chain is not changed."]
+            pub fn $peek(&self) -> Option<$ty> {
+                let mut buf = [0u8; mem::size_of::<$ty>()];
+                if self.copy_prefix(&mut buf) {
+                    Some($ty::$from(buf))
+                } else {
+                    None
+                }
+            }
+
+            #[doc = concat!("Removes ", $desc, " from the beginning of chain and returns it, ")]
+            #[doc = "or returns None if chain does not have enough data."]
+            pub fn $get(&mut self) -> Option<$ty> {
+                let v = self.$peek()?;
+                self.drain(mem::size_of::<$ty>());
+                Some(v)
+            }
+
+            #[doc = concat!("Appends ", $desc, " to the end of chain, writing it to the ")]
+            #[doc = "room left in the last node if possible (see `reserve`)."]
+            pub fn $put(&mut self, v: $ty) {
+                let data = v.$to();
+                self.reserve(data.len()).copy_from_slice(&data);
+                self.written(data.len());
+            }
+        )*
+    };
+}
+
+/// Typed accessors for binary protocols: fixed size numbers in either
+/// byte order and LEB128 varints.
+/// # Example
+/// ```
+/// use chainbuf::Chain;
+/// let mut chain = Chain::new();
+/// chain.put_u16_be(0xcafe);
+/// chain.put_u32_le(7);
+/// chain.put_varint(300);
+/// assert_eq!(chain.len(), 8);
+/// assert_eq!(chain.peek_u8(), Some(0xca));
+/// assert_eq!(chain.get_u16_be(), Some(0xcafe));
+/// assert_eq!(chain.get_u32_le(), Some(7));
+/// assert_eq!(chain.get_varint().unwrap(), Some(300));
+/// assert_eq!(chain.get_u8(), None);
+/// ```
+impl<'src, F: Flavor> Chain<'src, F> {
+    binary_accessors! {
+        u8, "`u8`", peek_u8, get_u8, put_u8, from_be_bytes, to_be_bytes;
+        i8, "`i8`", peek_i8, get_i8, put_i8, from_be_bytes, to_be_bytes;
+        u16, "big-endian `u16`", peek_u16_be, get_u16_be, put_u16_be, from_be_bytes, to_be_bytes;
+        u16, "little-endian `u16`", peek_u16_le, get_u16_le, put_u16_le, from_le_bytes, to_le_bytes;
+        i16, "big-endian `i16`", peek_i16_be, get_i16_be, put_i16_be, from_be_bytes, to_be_bytes;
+        i16, "little-endian `i16`", peek_i16_le, get_i16_le, put_i16_le, from_le_bytes, to_le_bytes;
+        u32, "big-endian `u32`", peek_u32_be, get_u32_be, put_u32_be, from_be_bytes, to_be_bytes;
+        u32, "little-endian `u32`", peek_u32_le, get_u32_le, put_u32_le, from_le_bytes, to_le_bytes;
+        i32, "big-endian `i32`", peek_i32_be, get_i32_be, put_i32_be, from_be_bytes, to_be_bytes;
+        i32, "little-endian `i32`", peek_i32_le, get_i32_le, put_i32_le, from_le_bytes, to_le_bytes;
+        u64, "big-endian `u64`", peek_u64_be, get_u64_be, put_u64_be, from_be_bytes, to_be_bytes;
+        u64, "little-endian `u64`", peek_u64_le, get_u64_le, put_u64_le, from_le_bytes, to_le_bytes;
+        i64, "big-endian `i64`", peek_i64_be, get_i64_be, put_i64_be, from_be_bytes, to_be_bytes;
+        i64, "little-endian `i64`", peek_i64_le, get_i64_le, put_i64_le, from_le_bytes, to_le_bytes;
+        u128, "big-endian `u128`", peek_u128_be, get_u128_be, put_u128_be, from_be_bytes, to_be_bytes;
+        u128, "little-endian `u128`", peek_u128_le, get_u128_le, put_u128_le, from_le_bytes, to_le_bytes;
+        i128, "big-endian `i128`", peek_i128_be, get_i128_be, put_i128_be, from_be_bytes, to_be_bytes;
+        i128, "little-endian `i128`", peek_i128_le, get_i128_le, put_i128_le, from_le_bytes, to_le_bytes;
+        f32, "big-endian `f32`", peek_f32_be, get_f32_be, put_f32_be, from_be_bytes, to_be_bytes;
+        f32, "little-endian `f32`", peek_f32_le, get_f32_le, put_f32_le, from_le_bytes, to_le_bytes;
+        f64, "big-endian `f64`", peek_f64_be, get_f64_be, put_f64_be, from_be_bytes, to_be_bytes;
+        f64, "little-endian `f64`", peek_f64_le, get_f64_le, put_f64_le, from_le_bytes, to_le_bytes;
+    }
+
+    /// Decodes LEB128 (protobuf) varint from the beginning of chain and
+    /// returns its value and encoded size. Returns `Ok(None)` if varint is
+    /// not complete yet and fails with `InvalidData` if it doesn't fit
+    /// into `u64`. Chain is not changed.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes(&[0xac]);
+    /// assert_eq!(chain.peek_varint().unwrap(), None);
+    /// chain.append_bytes(&[0x02]);
+    /// assert_eq!(chain.peek_varint().unwrap(), Some((300, 2)));
+    /// ```
+    pub fn peek_varint(&self) -> io::Result<Option<(u64, usize)>> {
+        let mut v = 0u64;
+        let mut size = 0;
+        for data in self.segments_from(0) {
+            for &b in data {
+                // 10th byte may only hold the highest bit of u64
+                if size == MAX_VARINT_LEN - 1 && b > 1 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "varint is too long",
+                    ));
+                }
+                v |= u64::from(b & 0x7f) << (7 * size);
+                size += 1;
+                if b & 0x80 == 0 {
+                    return Ok(Some((v, size)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Removes LEB128 varint from the beginning of chain and returns it,
+    /// see `peek_varint`. Chain is not changed if varint is not complete
+    /// or invalid.
+    pub fn get_varint(&mut self) -> io::Result<Option<u64>> {
+        Ok(self.peek_varint()?.map(|(v, size)| {
+            self.drain(size);
+            v
+        }))
+    }
+
+    /// Appends *v* encoded as LEB128 varint to the end of chain.
+    pub fn put_varint(&mut self, mut v: u64) {
+        let mut buf = [0u8; MAX_VARINT_LEN];
+        let mut size = 0;
+        while v >= 0x80 {
+            buf[size] = (v as u8) | 0x80;
+            v >>= 7;
+            size += 1;
+        }
+        buf[size] = v as u8;
+        size += 1;
+        self.reserve(size).copy_from_slice(&buf[..size]);
+        self.written(size);
+    }
+
+    /// Removes zigzag encoded LEB128 varint (protobuf `sint64`) from the
+    /// beginning of chain and returns it, see `get_varint`.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.put_varint_i64(-2);
+    /// assert_eq!(chain.peek_u8(), Some(3));
+    /// assert_eq!(chain.get_varint_i64().unwrap(), Some(-2));
+    /// ```
+    pub fn get_varint_i64(&mut self) -> io::Result<Option<i64>> {
+        Ok(self
+            .get_varint()?
+            .map(|v| ((v >> 1) as i64) ^ -((v & 1) as i64)))
+    }
+
+    /// Appends *v* encoded as zigzag LEB128 varint (protobuf `sint64`) to
+    /// the end of chain.
+    pub fn put_varint_i64(&mut self, v: i64) {
+        self.put_varint(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    /// Copies first `buf.len()` bytes of chain to *buf*. Returns false if
+    /// chain does not have enough data.
+    fn copy_prefix(&self, buf: &mut [u8]) -> bool {
+        if self.len() < buf.len() {
+            return false;
+        }
+        let mut copied = 0;
+        for data in self.segments_from(0) {
+            if copied == buf.len() {
+                break;
+            }
+            let n = cmp::min(data.len(), buf.len() - copied);
+            buf[copied..copied + n].copy_from_slice(&data[..n]);
+            copied += n;
+        }
+        true
+    }
+}
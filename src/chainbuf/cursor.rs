@@ -0,0 +1,171 @@
+use std::cmp;
+use std::io;
+use std::io::{BufRead, Read, Seek, SeekFrom};
+
+use super::{Chain, Flavor, Local, Node};
+
+/// Read-only cursor over chain data.
+/// Cursor borrows chain and remembers node and offset inside it, so
+/// sequential reads do not rescan chain from the beginning, and nothing
+/// is drained from chain. Implements `Read`, `BufRead` and `Seek`;
+/// seeking takes O(log n) of chain nodes.
+/// # Example
+/// ```
+/// use chainbuf::Chain;
+/// use std::io::{Read, Seek, SeekFrom};
+/// let mut chain = Chain::new();
+/// chain.append_bytes("hello".as_bytes());
+/// chain.append_bytes("world".as_bytes());
+/// let mut cursor = chain.cursor();
+/// let mut buf = [0u8; 4];
+/// cursor.seek(SeekFrom::Start(3)).unwrap();
+/// cursor.read_exact(&mut buf).unwrap();
+/// assert_eq!(&buf[..], "lowo".as_bytes());
+/// assert_eq!(cursor.position(), 7);
+/// assert_eq!(chain.len(), 10); // nothing drained
+/// ```
+pub struct ChainCursor<'a, 'src: 'a, F: Flavor = Local> {
+    chain: &'a Chain<'src, F>,
+    idx: usize,    // index of current node
+    offset: usize, // offset inside current node
+    pos: usize,    // position inside chain
+}
+
+impl<'src, F: Flavor> Chain<'src, F> {
+    /// Returns cursor positioned at the beginning of chain.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("helloworld".as_bytes());
+    /// let cursor = chain.cursor();
+    /// assert_eq!(cursor.position(), 0);
+    /// ```
+    pub fn cursor(&self) -> ChainCursor<'_, 'src, F> {
+        ChainCursor::new(self)
+    }
+}
+
+impl<'a, 'src, F: Flavor> ChainCursor<'a, 'src, F> {
+    fn new(chain: &'a Chain<'src, F>) -> ChainCursor<'a, 'src, F> {
+        let mut cursor = ChainCursor {
+            chain,
+            idx: 0,
+            offset: 0,
+            pos: 0,
+        };
+        cursor.skip_exhausted();
+        cursor
+    }
+
+    /// Returns current position of cursor inside chain.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns number of bytes between cursor and the end of chain.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// use std::io::BufRead;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("helloworld".as_bytes());
+    /// let mut cursor = chain.cursor();
+    /// cursor.consume(4);
+    /// assert_eq!(cursor.remaining(), 6);
+    /// ```
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.chain.len().saturating_sub(self.pos)
+    }
+
+    /// Moves cursor *size* bytes forward.
+    fn advance(&mut self, size: usize) {
+        self.offset += size;
+        self.pos += size;
+        self.skip_exhausted();
+    }
+
+    /// Moves cursor to absolute position *pos*.
+    fn move_to(&mut self, pos: usize) {
+        let (idx, offset) = self
+            .chain
+            .head
+            .locate(pos)
+            .unwrap_or((self.chain.head.len(), 0));
+        self.idx = idx;
+        self.offset = offset;
+        self.pos = pos;
+    }
+
+    /// Returns current node or None if cursor is at the end of chain.
+    #[inline]
+    fn node(&self) -> Option<&'a Node<'src, F>> {
+        self.chain.head.get(self.idx)
+    }
+
+    /// Maintains invariant: current node is either None, or has data
+    /// after offset.
+    fn skip_exhausted(&mut self) {
+        while let Some(node) = self.node() {
+            if self.offset < node.size() {
+                break;
+            }
+            self.offset -= node.size();
+            self.idx += 1;
+        }
+    }
+}
+
+impl<'a, 'src, F: Flavor> Read for ChainCursor<'a, 'src, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut copied = 0;
+        while copied < buf.len() {
+            let node = match self.node() {
+                Some(nd) => nd,
+                None => break,
+            };
+            let tocopy = cmp::min(node.size() - self.offset, buf.len() - copied);
+            buf[copied..copied + tocopy].copy_from_slice(node.get_data_from(self.offset, tocopy));
+            copied += tocopy;
+            self.advance(tocopy);
+        }
+        Ok(copied)
+    }
+}
+
+impl<'a, 'src, F: Flavor> BufRead for ChainCursor<'a, 'src, F> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self.node() {
+            Some(node) => Ok(node.get_data_from(self.offset, node.size() - self.offset)),
+            None => Ok(&[]),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.advance(amt);
+    }
+}
+
+/// Seeking past the end of chain is allowed, reads will return 0 bytes
+/// then.
+impl<'a, 'src, F: Flavor> Seek for ChainCursor<'a, 'src, F> {
+    fn seek(&mut self, style: SeekFrom) -> io::Result<u64> {
+        let (base, offs) = match style {
+            SeekFrom::Start(n) => (0, n as i64),
+            SeekFrom::End(n) => (self.chain.len() as i64, n),
+            SeekFrom::Current(n) => (self.pos as i64, n),
+        };
+        match base.checked_add(offs) {
+            Some(n) if n >= 0 => {
+                self.move_to(n as usize);
+                Ok(n as u64)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::ops::RangeBounds;
+
+use super::{Chain, Flavor};
+
+// Editing in the middle of chain splits nodes at edit points, so it takes
+// O(number of nodes) and never moves existing data.
+impl<'src, F: Flavor> Chain<'src, F> {
+    /// Inserts copy of *data* at offset *offs*.
+    /// # Panics
+    /// Panics if *offs* is greater than size of chain.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("Host: a\r\n\r\n".as_bytes());
+    /// chain.insert_bytes(9, "Via: b\r\n".as_bytes());
+    /// assert_eq!(chain.pullup_all().unwrap(), "Host: a\r\nVia: b\r\n\r\n".as_bytes());
+    /// ```
+    pub fn insert_bytes(&mut self, offs: usize, data: &[u8]) {
+        let mut inserted = Chain::default();
+        inserted.append_bytes(data);
+        self.insert_chain(offs, inserted);
+    }
+
+    /// Inserts all data of *chain* at offset *offs*, no data copy happens.
+    /// # Panics
+    /// Panics if *offs* is greater than size of chain.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("helloworld".as_bytes());
+    /// let mut space = Chain::new();
+    /// space.append_slice(" ".as_bytes());
+    /// chain.insert_chain(5, space);
+    /// assert_eq!(chain.pullup_all().unwrap(), "hello world".as_bytes());
+    /// ```
+    pub fn insert_chain(&mut self, offs: usize, chain: Chain<'src, F>) {
+        self.splice(offs..offs, chain);
+    }
+
+    /// Removes data in *range* from chain.
+    /// # Panics
+    /// Panics if *range* is out of bounds of chain, see `slice`.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("Host: a\r\nCookie: c\r\n\r\n".as_bytes());
+    /// chain.remove_range(9..20);
+    /// assert_eq!(chain.pullup_all().unwrap(), "Host: a\r\n\r\n".as_bytes());
+    /// ```
+    pub fn remove_range<R: RangeBounds<usize>>(&mut self, range: R) {
+        self.splice(range, Chain::default());
+    }
+
+    /// Replaces data in *range* with all data of *replacement*, no data
+    /// copy happens.
+    /// # Panics
+    /// Panics if *range* is out of bounds of chain, see `slice`.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("Connection: close\r\n".as_bytes());
+    /// let mut value = Chain::new();
+    /// value.append_slice("keep-alive".as_bytes());
+    /// chain.splice(12..17, value);
+    /// assert_eq!(chain.pullup_all().unwrap(), "Connection: keep-alive\r\n".as_bytes());
+    /// ```
+    pub fn splice<R: RangeBounds<usize>>(&mut self, range: R, replacement: Chain<'src, F>) {
+        let (from, to) = self.range_offsets(range);
+        if from == 0 {
+            // Edit at the head of chain is drain and prepend
+            self.consumed = self
+                .consumed
+                .wrapping_add(to)
+                .wrapping_sub(replacement.len());
+        }
+        let mut tail = self.split_off(from);
+        tail.drain(to - from);
+        self.concat(replacement);
+        self.concat(tail);
+    }
+}
@@ -0,0 +1,138 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+use super::{DataHolder, MutableDataHolder};
+
+/// Flavor of reference counting, that chain uses to share data holders
+/// between nodes and chains.
+///
+/// `Local` flavor uses `Rc` and is the default one. `Atomic` flavor uses
+/// `Arc`, so chains with this flavor (see `SyncChain`) are `Send` and `Sync`
+/// and can be passed to, or shared with, other threads.
+/// This trait is sealed and cannot be implemented outside of this crate.
+pub trait Flavor: Pointers {}
+
+/// Single-threaded flavor of chain, backed by `Rc`.
+pub struct Local;
+
+/// Thread-safe flavor of chain, backed by `Arc`.
+pub struct Atomic;
+
+impl Flavor for Local {}
+
+impl Flavor for Atomic {}
+
+/// Types of reference counted pointers to data holders and operations on
+/// them.
+// XXX: public only to be usable in bounds of public `Flavor`, not
+// reachable from outside of crate.
+pub trait Pointers {
+    /// Pointer to mutable data holder.
+    type Mutable: Clone;
+    /// Pointer to immutable data holder.
+    type Immutable: Clone;
+
+    /// Wraps *holder* in reference counted pointer.
+    fn new_mutable<H>(holder: H) -> Self::Mutable
+    where
+        H: MutableDataHolder + Send + Sync + 'static;
+
+    /// Wraps *holder* in reference counted pointer.
+    fn new_immutable<H>(holder: H) -> Self::Immutable
+    where
+        H: DataHolder + Send + Sync + 'static;
+
+    /// Returns data holder behind pointer.
+    fn mutable(ptr: &Self::Mutable) -> &dyn MutableDataHolder;
+
+    /// Returns data holder behind pointer or None, if it's shared.
+    fn mutable_mut(ptr: &mut Self::Mutable) -> Option<&mut dyn MutableDataHolder>;
+
+    /// Returns true if pointer is the only reference to data holder.
+    fn is_unique(ptr: &Self::Mutable) -> bool;
+
+    /// Returns data holder behind pointer.
+    fn immutable(ptr: &Self::Immutable) -> &dyn DataHolder;
+}
+
+impl Pointers for Local {
+    type Mutable = Rc<dyn MutableDataHolder>;
+    type Immutable = Rc<dyn DataHolder>;
+
+    #[inline]
+    fn new_mutable<H>(holder: H) -> Self::Mutable
+    where
+        H: MutableDataHolder + Send + Sync + 'static,
+    {
+        Rc::new(holder)
+    }
+
+    #[inline]
+    fn new_immutable<H>(holder: H) -> Self::Immutable
+    where
+        H: DataHolder + Send + Sync + 'static,
+    {
+        Rc::new(holder)
+    }
+
+    #[inline]
+    fn mutable(ptr: &Self::Mutable) -> &dyn MutableDataHolder {
+        &**ptr
+    }
+
+    #[inline]
+    fn mutable_mut(ptr: &mut Self::Mutable) -> Option<&mut dyn MutableDataHolder> {
+        Rc::get_mut(ptr).map(|h| h as &mut dyn MutableDataHolder)
+    }
+
+    #[inline]
+    fn is_unique(ptr: &Self::Mutable) -> bool {
+        Rc::weak_count(ptr) == 0 && Rc::strong_count(ptr) == 1
+    }
+
+    #[inline]
+    fn immutable(ptr: &Self::Immutable) -> &dyn DataHolder {
+        &**ptr
+    }
+}
+
+impl Pointers for Atomic {
+    type Mutable = Arc<dyn MutableDataHolder + Send + Sync>;
+    type Immutable = Arc<dyn DataHolder + Send + Sync>;
+
+    #[inline]
+    fn new_mutable<H>(holder: H) -> Self::Mutable
+    where
+        H: MutableDataHolder + Send + Sync + 'static,
+    {
+        Arc::new(holder)
+    }
+
+    #[inline]
+    fn new_immutable<H>(holder: H) -> Self::Immutable
+    where
+        H: DataHolder + Send + Sync + 'static,
+    {
+        Arc::new(holder)
+    }
+
+    #[inline]
+    fn mutable(ptr: &Self::Mutable) -> &dyn MutableDataHolder {
+        &**ptr
+    }
+
+    #[inline]
+    fn mutable_mut(ptr: &mut Self::Mutable) -> Option<&mut dyn MutableDataHolder> {
+        Arc::get_mut(ptr).map(|h| h as &mut dyn MutableDataHolder)
+    }
+
+    #[inline]
+    fn is_unique(ptr: &Self::Mutable) -> bool {
+        Arc::weak_count(ptr) == 0 && Arc::strong_count(ptr) == 1
+    }
+
+    #[inline]
+    fn immutable(ptr: &Self::Immutable) -> &dyn DataHolder {
+        &**ptr
+    }
+}
@@ -0,0 +1,102 @@
+use std::cmp;
+use std::io;
+use std::io::{BufRead, IoSlice, Read, Write};
+
+use super::{Chain, Flavor};
+
+/// Reading from chain copies data from its beginning and drains
+/// everything that has been read.
+/// # Example
+/// ```
+/// use chainbuf::Chain;
+/// use std::io::Read;
+/// let mut chain = Chain::new();
+/// chain.append_bytes("helloworld".as_bytes());
+/// let mut buf = [0u8; 5];
+/// assert_eq!(chain.read(&mut buf).unwrap(), 5);
+/// assert_eq!(&buf[..], "hello".as_bytes());
+/// assert_eq!(chain.len(), 5);
+/// ```
+impl<'src, F: Flavor> Read for Chain<'src, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut copied = 0;
+        for node in self.head.iter() {
+            if copied == buf.len() {
+                break;
+            }
+            let tocopy = cmp::min(node.size(), buf.len() - copied);
+            buf[copied..copied + tocopy].copy_from_slice(node.get_data_from_start(tocopy));
+            copied += tocopy;
+        }
+        self.drain(copied);
+        Ok(copied)
+    }
+}
+
+/// Writing to chain appends data to its end, so chain can be used as
+/// destination for any `std::io` writer.
+/// # Example
+/// ```
+/// use chainbuf::Chain;
+/// use std::io::Write;
+/// let mut chain = Chain::new();
+/// write!(chain, "hello{}", "world").unwrap();
+/// assert_eq!(chain.pullup_all().unwrap(), "helloworld".as_bytes());
+/// ```
+impl<'src, F: Flavor> Write for Chain<'src, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            self.append_bytes(buf);
+        }
+        Ok(buf.len())
+    }
+
+    /// Copies all slices into single reserved region, so they end up
+    /// in one node.
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let total = bufs.iter().map(|b| b.len()).sum();
+        if total == 0 {
+            return Ok(0);
+        }
+        {
+            let dst = self.reserve(total);
+            let mut offs = 0;
+            for b in bufs.iter() {
+                dst[offs..offs + b.len()].copy_from_slice(b);
+                offs += b.len();
+            }
+        }
+        self.written(total);
+        Ok(total)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `fill_buf` returns contiguous data of the first non-empty node,
+/// `consume` drains chain.
+/// # Example
+/// ```
+/// use chainbuf::Chain;
+/// use std::io::BufRead;
+/// let mut chain = Chain::new();
+/// chain.append_bytes("hello".as_bytes());
+/// chain.append_slice("world".as_bytes());
+/// assert_eq!(chain.fill_buf().unwrap(), "hello".as_bytes());
+/// chain.consume(5);
+/// assert_eq!(chain.fill_buf().unwrap(), "world".as_bytes());
+/// ```
+impl<'src, F: Flavor> BufRead for Chain<'src, F> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self.head.iter().find(|n| n.size() > 0) {
+            Some(node) => Ok(node.get_data_from_start(node.size())),
+            None => Ok(&[]),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.drain(amt);
+    }
+}
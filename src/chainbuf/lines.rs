@@ -0,0 +1,134 @@
+use std::io;
+
+use super::{Chain, Flavor, Local};
+
+/// Iterator over complete lines of chain, see `Chain::chain_lines`.
+pub struct Lines<'a, 'src: 'a, F: Flavor = Local> {
+    chain: &'a Chain<'src, F>,
+    pos: usize,
+}
+
+impl<'a, 'src, F: Flavor> Iterator for Lines<'a, 'src, F> {
+    type Item = Chain<'src, F>;
+
+    fn next(&mut self) -> Option<Chain<'src, F>> {
+        let (end, next) = self.chain.line_end(self.pos)?;
+        let line = self.chain.share_range(self.pos, end);
+        self.pos = next;
+        Some(line)
+    }
+}
+
+impl<'src, F: Flavor> Chain<'src, F> {
+    /// Returns iterator over complete lines of chain, terminated by `\n`
+    /// or `\r\n`. Lines are new chains without terminators, sharing data
+    /// holders with this one. Data after the last `\n` is not a complete
+    /// line yet, so it's not returned. Chain is not changed, see
+    /// `next_line` to drain lines.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("HELO a\r\nMAIL ".as_bytes());
+    /// chain.append_slice("b\n\nRCPT".as_bytes());
+    /// let lines: Vec<Vec<u8>> = chain
+    ///     .chain_lines()
+    ///     .map(|l| l.copy_bytes_from(0, l.len()))
+    ///     .collect();
+    /// assert_eq!(lines, vec![b"HELO a".to_vec(), b"MAIL b".to_vec(), vec![]]);
+    /// ```
+    pub fn chain_lines<'a>(&'a self) -> Lines<'a, 'src, F> {
+        Lines {
+            chain: self,
+            pos: 0,
+        }
+    }
+
+    /// Removes first complete line from chain and returns it without
+    /// terminator (`\n` or `\r\n`). Returns `Ok(None)` and leaves chain
+    /// intact if there is no complete line yet.
+    /// Fails with `InvalidData` if line is longer than *max* (None means no
+    /// limit), including the case when there is no terminator in the first
+    /// `max + 1` bytes, so chain doesn't grow forever waiting for it.
+    /// Chain is not changed on error.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("PING\r\nPI".as_bytes());
+    /// let line = chain.next_line(Some(8)).unwrap().unwrap();
+    /// assert_eq!(line.peek(4).unwrap(), "PING".as_bytes());
+    /// assert!(chain.next_line(Some(8)).unwrap().is_none());
+    /// chain.append_bytes("NGPINGPING".as_bytes());
+    /// assert!(chain.next_line(Some(8)).is_err());
+    /// ```
+    pub fn next_line(&mut self, max: Option<usize>) -> io::Result<Option<Chain<'src, F>>> {
+        match self.line_end(0) {
+            Some((end, next)) => {
+                check_line_length(end, max)?;
+                let line = self.share_range(0, end);
+                self.drain(next);
+                Ok(Some(line))
+            }
+            None => {
+                // Terminator may start at the last byte (`\r`)
+                check_line_length(self.len().saturating_sub(1), max)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Removes data up to and including first occurence of *delim* from
+    /// chain and returns it. Returns `Ok(None)` and leaves chain intact if
+    /// *delim* is not found. Fails with `InvalidData` if data before
+    /// *delim* is longer than *max*, see `next_line`.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("{\"a\":1}\0{\"b\"".as_bytes());
+    /// let msg = chain.next_until(b"\0", None).unwrap().unwrap();
+    /// assert_eq!(msg.len(), 8);
+    /// assert!(chain.next_until(b"\0", None).unwrap().is_none());
+    /// assert_eq!(chain.len(), 4);
+    /// ```
+    pub fn next_until(
+        &mut self,
+        delim: &[u8],
+        max: Option<usize>,
+    ) -> io::Result<Option<Chain<'src, F>>> {
+        match self.find(delim) {
+            Some(offs) => {
+                check_line_length(offs, max)?;
+                let end = offs + delim.len();
+                let data = self.share_range(0, end);
+                self.drain(end);
+                Ok(Some(data))
+            }
+            None => {
+                // *delim* may start in the last `delim.len() - 1` bytes
+                let pending = (self.len() + 1).saturating_sub(delim.len());
+                check_line_length(pending, max)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Finds end of line, starting at *offs*, and returns its offset
+    /// without terminator and offset of the next line.
+    fn line_end(&self, offs: usize) -> Option<(usize, usize)> {
+        let nl = self.find_from(offs, b"\n")?;
+        let cr = nl > offs && self.peek_from(nl - 1, 1).as_deref() == Some(b"\r");
+        Some((if cr { nl - 1 } else { nl }, nl + 1))
+    }
+}
+
+fn check_line_length(len: usize, max: Option<usize>) -> io::Result<()> {
+    match max {
+        Some(max) if len > max => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "line is longer than max",
+        )),
+        _ => Ok(()),
+    }
+}
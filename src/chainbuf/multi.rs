@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+
+use super::{Chain, Flavor};
+
+const ROOT: usize = 0;
+
+/// Set of patterns, compiled into Aho–Corasick automaton, to search for
+/// any of them in chain at once (see `Chain::find_multi`).
+///
+/// Automaton is built once and can be reused for any number of searches.
+/// It's a full DFA with transition table of 256 entries per state, so it
+/// suits short patterns, like delimiters or multipart boundaries.
+/// # Example
+/// ```
+/// use chainbuf::{Chain, PatternSet};
+/// let delims = PatternSet::new(&["\r\n", "\n", "\0"]);
+/// let mut chain = Chain::new();
+/// chain.append_bytes("key=value\r".as_bytes());
+/// chain.append_slice("\nnext".as_bytes());
+/// assert_eq!(chain.find_multi(&delims), Some((0, 9)));
+/// ```
+pub struct PatternSet {
+    // Transitions, 256 per state
+    delta: Vec<usize>,
+    // Index and length of the longest pattern ending in state
+    out: Vec<Option<(usize, usize)>>,
+}
+
+impl PatternSet {
+    /// Builds automaton for *patterns*. Pattern is identified by its index
+    /// in *patterns*; if some pattern is repeated, the first index is
+    /// reported.
+    pub fn new<I, P>(patterns: I) -> PatternSet
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<[u8]>,
+    {
+        // Build trie: 0 in transitions means no edge, as root can't be
+        // target of an edge.
+        let mut delta = vec![ROOT; 256];
+        let mut out = vec![None];
+        for (idx, p) in patterns.into_iter().enumerate() {
+            let p = p.as_ref();
+            let mut state = ROOT;
+            for &b in p {
+                let next = delta[state * 256 + b as usize];
+                state = if next == ROOT {
+                    let new = out.len();
+                    delta[state * 256 + b as usize] = new;
+                    delta.extend_from_slice(&[ROOT; 256]);
+                    out.push(None);
+                    new
+                } else {
+                    next
+                };
+            }
+            if out[state].is_none() {
+                out[state] = Some((idx, p.len()));
+            }
+        }
+
+        // Turn trie into DFA, visiting states in BFS order, so failure
+        // state of each is complete by the time we get to it.
+        let mut fail = vec![ROOT; out.len()];
+        let mut queue = VecDeque::new();
+        queue.extend(delta[..256].iter().cloned().filter(|&next| next != ROOT));
+        while let Some(state) = queue.pop_front() {
+            let f = fail[state];
+            // Own pattern is longer than any pattern ending in failure state
+            if out[state].is_none() {
+                out[state] = out[f];
+            }
+            for b in 0..256 {
+                let next = delta[state * 256 + b];
+                if next == ROOT {
+                    delta[state * 256 + b] = delta[f * 256 + b];
+                } else {
+                    fail[next] = delta[f * 256 + b];
+                    queue.push_back(next);
+                }
+            }
+        }
+        PatternSet { delta, out }
+    }
+
+    #[inline]
+    fn step(&self, state: usize, b: u8) -> usize {
+        self.delta[state * 256 + b as usize]
+    }
+}
+
+impl<'src, F: Flavor> Chain<'src, F> {
+    /// Finds first match of any pattern from *patterns* inside the chain
+    /// in one pass and returns pattern index and offset of the match, or
+    /// None if nothing found.
+    /// Match that ends first wins; among matches ending at the same byte
+    /// the longest one is reported.
+    /// # Example
+    /// ```
+    /// use chainbuf::{Chain, PatternSet};
+    /// let boundaries = PatternSet::new(&["--frontier", "--frontier--"]);
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("preamble\r\n--fron".as_bytes());
+    /// chain.append_slice("tier\r\nbody".as_bytes());
+    /// assert_eq!(chain.find_multi(&boundaries), Some((0, 10)));
+    /// ```
+    pub fn find_multi(&self, patterns: &PatternSet) -> Option<(usize, usize)> {
+        self.find_multi_from(0, patterns)
+    }
+
+    /// Same as `find_multi`, but starts search from offset *offs*. Returned
+    /// offset is counted from the beginning of chain.
+    /// # Example
+    /// ```
+    /// use chainbuf::{Chain, PatternSet};
+    /// let delims = PatternSet::new(&[";", ","]);
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("a;b,c".as_bytes());
+    /// assert_eq!(chain.find_multi_from(2, &delims), Some((1, 3)));
+    /// ```
+    pub fn find_multi_from(&self, offs: usize, patterns: &PatternSet) -> Option<(usize, usize)> {
+        if offs > self.len() {
+            return None;
+        }
+        if let Some((idx, _)) = patterns.out[ROOT] {
+            // Empty pattern
+            return Some((idx, offs));
+        }
+        let mut state = ROOT;
+        let mut pos = offs;
+        for data in self.segments_from(offs) {
+            for &b in data {
+                state = patterns.step(state, b);
+                pos += 1;
+                if let Some((idx, len)) = patterns.out[state] {
+                    return Some((idx, pos - len));
+                }
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::vec_deque::{Iter, VecDeque};
+
+use super::{Flavor, Node};
+
+/// Deque of chain nodes indexed by offsets of their first bytes.
+///
+/// Offsets are kept in "virtual" coordinates and are not rewritten when
+/// nodes are removed from the front: position of node inside chain is its
+/// offset minus offset of the first node. Offset arithmetic is wrapping,
+/// so prepending never underflows.
+/// Finding node by position in chain takes O(log n) then.
+pub(super) struct NodeList<'src, F: Flavor> {
+    nodes: VecDeque<Node<'src, F>>,
+    starts: VecDeque<usize>,
+}
+
+impl<'src, F: Flavor> NodeList<'src, F> {
+    pub(super) fn new() -> NodeList<'src, F> {
+        NodeList {
+            nodes: VecDeque::new(),
+            starts: VecDeque::new(),
+        }
+    }
+
+    /// Returns number of nodes.
+    #[inline]
+    pub(super) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[inline]
+    pub(super) fn iter(&self) -> Iter<'_, Node<'src, F>> {
+        self.nodes.iter()
+    }
+
+    /// Iterates over nodes starting from node with index *from*.
+    #[inline]
+    pub(super) fn iter_from(&self, from: usize) -> Iter<'_, Node<'src, F>> {
+        self.nodes.range(from..)
+    }
+
+    #[inline]
+    pub(super) fn get(&self, idx: usize) -> Option<&Node<'src, F>> {
+        self.nodes.get(idx)
+    }
+
+    #[inline]
+    pub(super) fn front(&self) -> Option<&Node<'src, F>> {
+        self.nodes.front()
+    }
+
+    #[inline]
+    pub(super) fn back(&self) -> Option<&Node<'src, F>> {
+        self.nodes.back()
+    }
+
+    /// Returns first node. Caller should not move its `start`, use
+    /// `advance_front` and `retreat_front` for this.
+    #[inline]
+    pub(super) fn front_mut(&mut self) -> Option<&mut Node<'src, F>> {
+        self.nodes.front_mut()
+    }
+
+    /// Returns last node. Caller is free to move its `end`.
+    #[inline]
+    pub(super) fn back_mut(&mut self) -> Option<&mut Node<'src, F>> {
+        self.nodes.back_mut()
+    }
+
+    pub(super) fn push_back(&mut self, node: Node<'src, F>) {
+        let end = self.virtual_end();
+        self.nodes.push_back(node);
+        self.starts.push_back(end);
+    }
+
+    pub(super) fn push_front(&mut self, node: Node<'src, F>) {
+        let start = self.virtual_start().wrapping_sub(node.size());
+        self.nodes.push_front(node);
+        self.starts.push_front(start);
+    }
+
+    pub(super) fn pop_front(&mut self) -> Option<Node<'src, F>> {
+        self.starts.pop_front();
+        self.nodes.pop_front()
+    }
+
+    pub(super) fn pop_back(&mut self) -> Option<Node<'src, F>> {
+        self.starts.pop_back();
+        self.nodes.pop_back()
+    }
+
+    /// Moves start of the first node *size* bytes forward.
+    pub(super) fn advance_front(&mut self, size: usize) {
+        if let Some(node) = self.nodes.front_mut() {
+            node.start += size;
+            self.starts[0] = self.starts[0].wrapping_add(size);
+        }
+    }
+
+    /// Moves start of the first node *size* bytes backward.
+    pub(super) fn retreat_front(&mut self, size: usize) {
+        if let Some(node) = self.nodes.front_mut() {
+            node.start -= size;
+            self.starts[0] = self.starts[0].wrapping_sub(size);
+        }
+    }
+
+    /// Finds node containing byte at position *pos*. Returns index of
+    /// node and offset inside it, or None if *pos* is out of bounds.
+    pub(super) fn locate(&self, pos: usize) -> Option<(usize, usize)> {
+        let base = self.virtual_start();
+        let idx = self
+            .starts
+            .partition_point(|s| s.wrapping_sub(base) <= pos)
+            .checked_sub(1)?;
+        let offset = pos - self.starts[idx].wrapping_sub(base);
+        if offset < self.nodes[idx].size() {
+            Some((idx, offset))
+        } else {
+            None
+        }
+    }
+
+    /// Splits off first *pos* bytes and returns them as new list. If
+    /// *pos* falls inside a node, node is split in two, both halves
+    /// sharing the same data holder.
+    /// *pos* should be less than size of data in list.
+    pub(super) fn split_to(&mut self, pos: usize) -> NodeList<'src, F> {
+        let mut front = NodeList::new();
+        let (idx, offset) = match self.locate(pos) {
+            Some(loc) => loc,
+            None => return front,
+        };
+        if offset != 0 {
+            // Requested data in the middle of node, should split it then
+            let mut nn = self.nodes[idx].clone();
+            nn.start += offset;
+            self.nodes[idx].end = nn.start;
+            self.move_front_to(&mut front, idx + 1);
+            self.push_front(nn);
+        } else {
+            // Requested data right on the border of nodes, can move all
+            // nodes before this one
+            self.move_front_to(&mut front, idx);
+        }
+        front
+    }
+
+    /// Moves all nodes from *src* to the back of list.
+    pub(super) fn append(&mut self, src: &mut NodeList<'src, F>) {
+        if self.nodes.is_empty() {
+            std::mem::swap(self, src);
+        } else {
+            let n = src.len();
+            src.move_front_to(self, n);
+        }
+    }
+
+    /// Moves *n* nodes from the front of list to the back of *dst*.
+    fn move_front_to(&mut self, dst: &mut NodeList<'src, F>, n: usize) {
+        if n == 0 {
+            return;
+        }
+        let delta = dst.virtual_end().wrapping_sub(self.virtual_start());
+        dst.nodes.extend(self.nodes.drain(..n));
+        dst.starts
+            .extend(self.starts.drain(..n).map(|s| s.wrapping_add(delta)));
+    }
+
+    #[inline]
+    fn virtual_start(&self) -> usize {
+        self.starts.front().cloned().unwrap_or(0)
+    }
+
+    #[inline]
+    fn virtual_end(&self) -> usize {
+        match (self.starts.back(), self.nodes.back()) {
+            (Some(s), Some(n)) => s.wrapping_add(n.size()),
+            _ => 0,
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::cell::RefCell;
+
+use regex_automata::hybrid::dfa::{Cache, DFA};
+use regex_automata::hybrid::{BuildError, LazyStateID};
+use regex_automata::nfa::thompson;
+use regex_automata::util::start;
+use regex_automata::{Anchored, MatchKind};
+
+use super::{Chain, Flavor};
+
+// Lazy DFAs are built with default cache settings, so they never give up
+// and have no quit bytes.
+const NEVER_GIVES_UP: &str = "lazy DFA gave up on search";
+
+/// Regular expression compiled to search inside chain without `pullup`.
+///
+/// Regex is compiled into a pair of lazy DFAs: forward one finds where the
+/// leftmost match ends and reverse one, running back from there, finds
+/// where it starts. Both are fed with data of chain nodes chunk by chunk,
+/// so nodes are never copied. Semantics of matches are the same as in
+/// `regex` crate.
+/// Regex keeps mutable caches of DFA states inside, so it's not `Sync`;
+/// clone it to use in several threads.
+/// # Example
+/// ```
+/// use chainbuf::{Chain, Regex};
+/// let re = Regex::new(r"ERROR \d+").unwrap();
+/// let mut chain = Chain::new();
+/// chain.append_bytes("INFO 1\nERR".as_bytes());
+/// chain.append_slice("OR 42\n".as_bytes());
+/// let (start, end) = chain.find_regex(&re).unwrap();
+/// assert_eq!(chain.pullup_from(start, end - start).unwrap(), "ERROR 42".as_bytes());
+/// ```
+pub struct Regex {
+    fwd: DFA,
+    rev: DFA,
+    caches: RefCell<(Cache, Cache)>,
+}
+
+impl Regex {
+    /// Compiles *pattern*, see `regex` crate for syntax. Unicode word
+    /// boundaries are not supported by lazy DFAs, use ASCII ones,
+    /// `(?-u:\b)`, instead.
+    pub fn new(pattern: &str) -> Result<Regex, Box<BuildError>> {
+        let fwd = DFA::builder()
+            .configure(DFA::config().match_kind(MatchKind::LeftmostFirst))
+            .build(pattern)
+            .map_err(Box::new)?;
+        let rev = DFA::builder()
+            .configure(DFA::config().match_kind(MatchKind::All).prefilter(None))
+            .thompson(thompson::Config::new().reverse(true))
+            .build(pattern)
+            .map_err(Box::new)?;
+        let caches = RefCell::new((fwd.create_cache(), rev.create_cache()));
+        Ok(Regex { fwd, rev, caches })
+    }
+}
+
+impl Clone for Regex {
+    fn clone(&self) -> Regex {
+        Regex {
+            fwd: self.fwd.clone(),
+            rev: self.rev.clone(),
+            caches: RefCell::new((self.fwd.create_cache(), self.rev.create_cache())),
+        }
+    }
+}
+
+impl<'src, F: Flavor> Chain<'src, F> {
+    /// Finds leftmost match of *re* inside the chain and returns offsets of
+    /// its start and end (exclusive), or None if nothing found. Offsets can
+    /// be passed to `pullup_from` or `drain`.
+    /// # Example
+    /// ```
+    /// use chainbuf::{Chain, Regex};
+    /// let re = Regex::new(r"[a-z]+=\d+").unwrap();
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("; x=1".as_bytes());
+    /// assert_eq!(chain.find_regex(&re), Some((2, 5)));
+    /// ```
+    pub fn find_regex(&self, re: &Regex) -> Option<(usize, usize)> {
+        self.find_regex_from(0, re)
+    }
+
+    /// Same as `find_regex`, but starts search from offset *offs*. Data
+    /// before *offs* is only looked at by assertions like `^` or `\b`.
+    /// # Example
+    /// ```
+    /// use chainbuf::{Chain, Regex};
+    /// let re = Regex::new(r"\d+").unwrap();
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("12 345".as_bytes());
+    /// assert_eq!(chain.find_regex_from(1, &re), Some((1, 2)));
+    /// assert_eq!(chain.find_regex_from(2, &re), Some((3, 6)));
+    /// ```
+    pub fn find_regex_from(&self, offs: usize, re: &Regex) -> Option<(usize, usize)> {
+        if offs > self.len() {
+            return None;
+        }
+        let mut caches = re.caches.borrow_mut();
+        let (fwd_cache, rev_cache) = &mut *caches;
+        let end = self.regex_end(offs, &re.fwd, fwd_cache)?;
+        let start = self.regex_start(offs, end, &re.rev, rev_cache)?;
+        Some((start, end))
+    }
+
+    /// Runs forward DFA from *offs* and returns end of leftmost match.
+    fn regex_end(&self, offs: usize, dfa: &DFA, cache: &mut Cache) -> Option<usize> {
+        let config = start::Config::new()
+            .anchored(Anchored::No)
+            .look_behind(offs.checked_sub(1).map(|p| self.byte_at(p)));
+        let mut sid = dfa.start_state(cache, &config).expect(NEVER_GIVES_UP);
+        let mut end = None;
+        let mut pos = offs;
+        for data in self.segments_from(offs) {
+            for &b in data {
+                sid = next_state(dfa, cache, sid, b);
+                // Matches are delayed by one byte in DFA
+                if sid.is_match() {
+                    end = Some(pos);
+                } else if sid.is_dead() {
+                    return end;
+                }
+                pos += 1;
+            }
+        }
+        sid = dfa.next_eoi_state(cache, sid).expect(NEVER_GIVES_UP);
+        if sid.is_match() {
+            end = Some(pos);
+        }
+        end
+    }
+
+    /// Runs reverse DFA from *end* back to *offs* and returns start of
+    /// the match, that ends at *end*.
+    fn regex_start(&self, offs: usize, end: usize, dfa: &DFA, cache: &mut Cache) -> Option<usize> {
+        let look_behind = if end < self.len() {
+            Some(self.byte_at(end))
+        } else {
+            None
+        };
+        let config = start::Config::new()
+            .anchored(Anchored::Yes)
+            .look_behind(look_behind);
+        let mut sid = dfa.start_state(cache, &config).expect(NEVER_GIVES_UP);
+        let mut start = None;
+        if end > offs {
+            // infailable: end is inside chain
+            let (first, _) = self.head.locate(offs).unwrap();
+            let (last, last_offs) = self.head.locate(end - 1).unwrap();
+            let mut pos = end;
+            for idx in (first..=last).rev() {
+                let node = self.head.get(idx).unwrap();
+                let to = if idx == last {
+                    last_offs + 1
+                } else {
+                    node.size()
+                };
+                let from = to - std::cmp::min(to, pos - offs);
+                for &b in node.get_data_from(from, to - from).iter().rev() {
+                    pos -= 1;
+                    sid = next_state(dfa, cache, sid, b);
+                    if sid.is_match() {
+                        start = Some(pos + 1);
+                    } else if sid.is_dead() {
+                        return start;
+                    }
+                }
+            }
+        }
+        // Feed DFA with the byte before searched area or end of input
+        if offs > 0 {
+            sid = next_state(dfa, cache, sid, self.byte_at(offs - 1));
+            if sid.is_match() {
+                start = Some(offs);
+            }
+        } else {
+            sid = dfa.next_eoi_state(cache, sid).expect(NEVER_GIVES_UP);
+            if sid.is_match() {
+                start = Some(0);
+            }
+        }
+        start
+    }
+
+    /// Returns byte at position *pos*, which should be inside chain.
+    fn byte_at(&self, pos: usize) -> u8 {
+        let (idx, offs) = self.head.locate(pos).unwrap();
+        self.head.get(idx).unwrap().get_data_from(offs, 1)[0]
+    }
+}
+
+#[inline]
+fn next_state(dfa: &DFA, cache: &mut Cache, sid: LazyStateID, b: u8) -> LazyStateID {
+    dfa.next_state(cache, sid, b).expect(NEVER_GIVES_UP)
+}
@@ -0,0 +1,542 @@
+use std::cmp;
+
+use libc::{c_int, c_void};
+
+use super::{Chain, Flavor, Local};
+
+/// Needles shorter than this are searched naively: building shift table
+/// costs more than it saves.
+const BMH_MIN_NEEDLE: usize = 4;
+
+/// Iterator over offsets of non-overlapping matches of needle in chain,
+/// see `Chain::match_indices`.
+pub struct MatchIndices<'a, 'src: 'a, F: Flavor = Local> {
+    chain: &'a Chain<'src, F>,
+    needle: &'a [u8],
+    pos: usize,
+}
+
+impl<'a, 'src, F: Flavor> Iterator for MatchIndices<'a, 'src, F> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let found = self.chain.find_from(self.pos, self.needle)?;
+        // Empty needle matches everywhere, step over it
+        self.pos = found + cmp::max(self.needle.len(), 1);
+        Some(found)
+    }
+}
+
+/// Incremental search of needle in chain, which grows over time.
+///
+/// Searcher remembers how far it has scanned chain and its partial match,
+/// so every call of `next` looks only at bytes appended after previous
+/// call. Draining bytes from the front of chain between calls is fine:
+/// offsets are always relative to the current beginning of chain. Data
+/// prepended to chain is not scanned. After data is removed from the end
+/// of chain or edited in the middle (`truncate`, `split_off`, `splice` and
+/// such), searcher scans changed data again; match cut by the edit is
+/// forgotten and can be found again. Only the latest edit is taken into
+/// account, so `next` should be called after each of them.
+/// Searcher uses Knuth–Morris–Pratt algorithm and should be used with
+/// the same chain all the time.
+/// # Example
+/// ```
+/// use chainbuf::{Chain, Searcher};
+/// let mut chain = Chain::new();
+/// let mut searcher = Searcher::new(b"\r\n\r\n");
+/// chain.append_bytes("GET / HTTP/1.1\r\n\r".as_bytes());
+/// assert_eq!(searcher.next(&chain), None);
+/// chain.drain(4);
+/// chain.append_bytes("\nbody".as_bytes());
+/// assert_eq!(searcher.next(&chain), Some(10));
+/// ```
+pub struct Searcher {
+    needle: Vec<u8>,
+    // Lengths of the longest proper borders of needle prefixes
+    borders: Vec<usize>,
+    // Length of needle prefix matched right before `pos`
+    matched: usize,
+    // Scanned position in chain coordinates, see `Chain::consumed`,
+    // None if nothing scanned yet
+    pos: Option<usize>,
+    // End of the last match in chain coordinates, None if there was none
+    last_end: Option<usize>,
+    // Value of `Chain::edits` at the time of the last scan
+    edits: usize,
+}
+
+impl Searcher {
+    /// Creates searcher of *needle*, starting from the beginning of chain.
+    pub fn new(needle: &[u8]) -> Searcher {
+        Searcher {
+            needle: needle.to_vec(),
+            borders: kmp_borders(needle, same_byte),
+            matched: 0,
+            pos: None,
+            last_end: None,
+            edits: 0,
+        }
+    }
+
+    /// Returns offset of the next match of needle in *chain* or None if
+    /// there is no match in chain yet. Matches do not overlap: search
+    /// continues after the end of returned one.
+    pub fn next<F: Flavor>(&mut self, chain: &Chain<'_, F>) -> Option<usize> {
+        if self.edits != chain.edits {
+            self.edits = chain.edits;
+            self.rewind_to_edit(chain.edited, chain.consumed.wrapping_add(chain.len()));
+        }
+        let mut offs = match self.pos {
+            Some(pos) => pos.wrapping_sub(chain.consumed),
+            None => 0,
+        };
+        if offs > chain.len() {
+            // Everything scanned was drained
+            offs = 0;
+            self.matched = 0;
+        }
+        // Matched bytes that were drained can't start a match anymore
+        while self.matched > offs {
+            self.matched = self.borders[self.matched - 1];
+        }
+        if self.needle.is_empty() {
+            self.pos = Some(chain.consumed.wrapping_add(offs + 1));
+            self.last_end = self.pos;
+            return if offs < chain.len() { Some(offs) } else { None };
+        }
+        let m = self.needle.len();
+        for data in chain.segments_from(offs) {
+            let mut i = 0;
+            while i < data.len() {
+                if self.matched == 0 {
+                    // Skip quickly to possible start of match
+                    match find_byte(&data[i..], self.needle[0]) {
+                        Some(skip) => i += skip,
+                        None => break,
+                    }
+                }
+                let b = data[i];
+                while self.matched > 0 && b != self.needle[self.matched] {
+                    self.matched = self.borders[self.matched - 1];
+                }
+                if b == self.needle[self.matched] {
+                    self.matched += 1;
+                }
+                i += 1;
+                if self.matched == m {
+                    let end = offs + i;
+                    self.matched = 0;
+                    self.pos = Some(chain.consumed.wrapping_add(end));
+                    self.last_end = self.pos;
+                    return Some(end - m);
+                }
+            }
+            offs += data.len();
+        }
+        self.pos = Some(chain.consumed.wrapping_add(offs));
+        None
+    }
+
+    /// Moves scanned position back to offset *edited* (in chain
+    /// coordinates), if data from there on was scanned: it could have been
+    /// removed or changed. Partial match at *edited* could start up to
+    /// m - 1 bytes before it, but not before the end of the last match,
+    /// unless the edit has cut that match. Position never goes past the
+    /// current end of chain *chain_end*.
+    fn rewind_to_edit(&mut self, edited: usize, chain_end: usize) {
+        let pos = match self.pos {
+            Some(pos) if before(edited, pos) => pos,
+            _ => return,
+        };
+        let m = self.needle.len();
+        let mut start = edited.wrapping_sub(m.saturating_sub(1));
+        if let Some(end) = self.last_end {
+            let floor = if before(edited, end) {
+                end.wrapping_sub(m)
+            } else {
+                end
+            };
+            if before(start, floor) {
+                start = floor;
+            }
+        }
+        // Data after the edit could have been removed with the last match
+        if before(chain_end, start) {
+            start = chain_end;
+        }
+        debug_assert!(!before(pos, start));
+        self.pos = Some(start);
+        self.matched = 0;
+    }
+
+    /// Forgets scanned data and partial match, next search will start
+    /// from the beginning of chain.
+    pub fn reset(&mut self) {
+        self.matched = 0;
+        self.pos = None;
+        self.last_end = None;
+    }
+}
+
+impl<'src, F: Flavor> Chain<'src, F> {
+    /// Finds sequence of bytes inside the chain, starting from offset
+    /// *offs*, and returns offset (from the beginning of chain) to first
+    /// symbol of sequence or None if nothing found.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("key: value\r\n".as_bytes());
+    /// chain.append_slice("key2: value2\r\n".as_bytes());
+    /// let first = chain.find(b"\r\n").unwrap();
+    /// assert_eq!(first, 10);
+    /// assert_eq!(chain.find_from(first + 2, b"\r\n"), Some(24));
+    /// ```
+    pub fn find_from(&self, offs: usize, needle: &[u8]) -> Option<usize> {
+        self.search_from(offs, needle, false)
+    }
+
+    /// Finds last occurence of sequence of bytes inside the chain and
+    /// returns offset to its first symbol or None if nothing found.
+    /// Chain is scanned backwards, from the end.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("a/b/c".as_bytes());
+    /// assert_eq!(chain.rfind(b"/"), Some(3));
+    /// assert_eq!(chain.rfind(b"//"), None);
+    /// chain.append_slice("aaa".as_bytes());
+    /// assert_eq!(chain.rfind(b"aa"), Some(6));
+    /// ```
+    pub fn rfind(&self, needle: &[u8]) -> Option<usize> {
+        let m = needle.len();
+        if m == 0 {
+            return Some(self.len());
+        }
+        // Last match of needle is the first match of reversed needle in
+        // reversed chain
+        let rneedle: Vec<u8> = needle.iter().rev().cloned().collect();
+        let borders = kmp_borders(&rneedle, same_byte);
+        let mut matched = 0;
+        let mut end = self.len();
+        for node in self.head.iter().rev() {
+            let data = node.get_data_from(0, node.size());
+            let mut i = data.len();
+            while i > 0 {
+                if matched == 0 {
+                    // Skip quickly to possible end of match
+                    match data[..i].iter().rposition(|&b| b == rneedle[0]) {
+                        Some(found) => i = found + 1,
+                        None => break,
+                    }
+                }
+                i -= 1;
+                kmp_step(data[i], &rneedle, &borders, &mut matched, same_byte);
+                if matched == m {
+                    return Some(end - data.len() + i);
+                }
+            }
+            end -= data.len();
+        }
+        None
+    }
+
+    /// Finds first occurence of byte *b* inside the chain.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("hello".as_bytes());
+    /// chain.append_slice("world".as_bytes());
+    /// assert_eq!(chain.find_byte(b'w'), Some(5));
+    /// ```
+    pub fn find_byte(&self, b: u8) -> Option<usize> {
+        let mut msum = 0;
+        for data in self.segments_from(0) {
+            if let Some(offs) = find_byte(data, b) {
+                return Some(msum + offs);
+            }
+            msum += data.len();
+        }
+        None
+    }
+
+    /// Finds first byte inside the chain, that is one of *bytes*.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("key=value;next".as_bytes());
+    /// assert_eq!(chain.find_any_of(b";="), Some(3));
+    /// ```
+    pub fn find_any_of(&self, bytes: &[u8]) -> Option<usize> {
+        let mut set = [false; 256];
+        for &b in bytes {
+            set[b as usize] = true;
+        }
+        let mut msum = 0;
+        for data in self.segments_from(0) {
+            if let Some(offs) = data.iter().position(|&b| set[b as usize]) {
+                return Some(msum + offs);
+            }
+            msum += data.len();
+        }
+        None
+    }
+
+    /// Returns iterator over offsets of all non-overlapping matches of
+    /// *needle* inside the chain.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("a\r\nb\r".as_bytes());
+    /// chain.append_slice("\nc\r\n".as_bytes());
+    /// let all: Vec<usize> = chain.match_indices(b"\r\n").collect();
+    /// assert_eq!(all, vec![1, 4, 7]);
+    /// ```
+    pub fn match_indices<'a>(&'a self, needle: &'a [u8]) -> MatchIndices<'a, 'src, F> {
+        MatchIndices {
+            chain: self,
+            needle,
+            pos: 0,
+        }
+    }
+
+    /// Finds sequence of bytes inside the chain ignoring ASCII case and
+    /// returns offset to first symbol of sequence or None if nothing found.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("Host: example.com\r\nCONTENT-".as_bytes());
+    /// chain.append_slice("Length: 0\r\n".as_bytes());
+    /// assert_eq!(chain.find_ignore_ascii_case(b"content-length:"), Some(19));
+    /// ```
+    pub fn find_ignore_ascii_case(&self, needle: &[u8]) -> Option<usize> {
+        self.search_from(0, needle, true)
+    }
+
+    /// Iterates over data of nodes, starting from offset *offs*.
+    pub(super) fn segments_from(&self, offs: usize) -> impl Iterator<Item = &[u8]> {
+        let (idx, skip) = self.head.locate(offs).unwrap_or((self.head.len(), 0));
+        self.head.iter_from(idx).enumerate().map(move |(i, n)| {
+            let skip = if i == 0 { skip } else { 0 };
+            n.get_data_from(skip, n.size() - skip)
+        })
+    }
+
+    fn search_from(&self, offs: usize, needle: &[u8], ignore_case: bool) -> Option<usize> {
+        if offs > self.len() {
+            return None;
+        }
+        if needle.is_empty() {
+            return Some(offs);
+        }
+        let (find, fold): (Finder, Fold) = if ignore_case {
+            (find_bytes_ignore_ascii_case, fold_ascii_case)
+        } else {
+            (find_bytes, same_byte)
+        };
+        let m = needle.len();
+        // Built on first node boundary, single node chains don't need it
+        let mut borders = None;
+        let mut msum = offs;
+        // Length of needle prefix matched at the end of data seen so far
+        let mut matched = 0;
+        let mut segments = self.segments_from(offs).peekable();
+        while let Some(node_data) = segments.next() {
+            // Match starting in previous nodes ends within m - 1 bytes
+            let head_len = cmp::min(node_data.len(), m - 1);
+            let continued = matched > 0;
+            if continued {
+                let head = &node_data[..head_len];
+                let borders = borders.get_or_insert_with(|| kmp_borders(needle, fold));
+                if let Some(end) = kmp_feed(head, needle, borders, &mut matched, fold) {
+                    return Some(msum + end - m);
+                }
+            }
+            if node_data.len() > head_len {
+                // Try to find entire needle in one node
+                if let Some(found) = find(node_data, needle) {
+                    return Some(msum + found);
+                }
+                matched = 0;
+            }
+            if segments.peek().is_none() {
+                break;
+            }
+            if node_data.len() > head_len {
+                // Prefix of needle matched at the end of node fits in its
+                // last m - 1 bytes, so it is looked for from scratch there
+                let tail = &node_data[node_data.len() - (m - 1)..];
+                let borders = borders.get_or_insert_with(|| kmp_borders(needle, fold));
+                kmp_feed(tail, needle, borders, &mut matched, fold);
+            } else if !continued {
+                // Whole short node is needed to know the state, unless it
+                // was fed already to continue partial match
+                let borders = borders.get_or_insert_with(|| kmp_borders(needle, fold));
+                kmp_feed(node_data, needle, borders, &mut matched, fold);
+            }
+            msum += node_data.len();
+        }
+
+        None
+    }
+}
+
+/// Returns true if chain coordinate *a* is before *b*, see
+/// `Chain::consumed`. Coordinates wrap, but distance between them is
+/// always less than `isize::MAX`.
+#[inline]
+fn before(a: usize, b: usize) -> bool {
+    (a.wrapping_sub(b) as isize) < 0
+}
+
+type Finder = fn(&[u8], &[u8]) -> Option<usize>;
+type Fold = fn(u8) -> u8;
+
+fn same_byte(b: u8) -> u8 {
+    b
+}
+
+fn fold_ascii_case(b: u8) -> u8 {
+    b.to_ascii_lowercase()
+}
+
+/// Returns lengths of the longest proper borders of *needle* prefixes
+/// (Knuth–Morris–Pratt failure function); bytes are compared after *fold*.
+fn kmp_borders(needle: &[u8], fold: Fold) -> Vec<usize> {
+    let mut borders = vec![0; needle.len()];
+    let mut k = 0;
+    for i in 1..needle.len() {
+        while k > 0 && fold(needle[i]) != fold(needle[k]) {
+            k = borders[k - 1];
+        }
+        if fold(needle[i]) == fold(needle[k]) {
+            k += 1;
+        }
+        borders[i] = k;
+    }
+    borders
+}
+
+/// Feeds *data* to Knuth–Morris–Pratt matcher of *needle*, that has
+/// *matched* bytes of needle matched. Returns offset in data right after
+/// the first complete match, or None and number of needle bytes matched
+/// at the end of data in *matched*.
+fn kmp_feed(
+    data: &[u8],
+    needle: &[u8],
+    borders: &[usize],
+    matched: &mut usize,
+    fold: Fold,
+) -> Option<usize> {
+    for (i, &b) in data.iter().enumerate() {
+        kmp_step(b, needle, borders, matched, fold);
+        if *matched == needle.len() {
+            *matched = 0;
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+/// Advances Knuth–Morris–Pratt matcher of *needle*, that has *matched*
+/// bytes of needle matched, by byte *b*.
+#[inline]
+fn kmp_step(b: u8, needle: &[u8], borders: &[usize], matched: &mut usize, fold: Fold) {
+    let b = fold(b);
+    while *matched > 0 && b != fold(needle[*matched]) {
+        *matched = borders[*matched - 1];
+    }
+    if b == fold(needle[*matched]) {
+        *matched += 1;
+    }
+}
+
+/// Finds subsequence of bytes in bytesequence. Returns offset or None
+/// if nothing found.
+/// Single byte needles are searched with `memchr`, longer ones with
+/// Boyer–Moore–Horspool.
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let m = needle.len();
+    if m == 0 {
+        return Some(0);
+    }
+    if m > haystack.len() {
+        return None;
+    }
+    if m == 1 {
+        return find_byte(haystack, needle[0]);
+    }
+    if m < BMH_MIN_NEEDLE {
+        return haystack.windows(m).position(|w| w == needle);
+    }
+    find_bmh(haystack, needle, |b| b, |a, b| a == b)
+}
+
+/// Same as `find_bytes`, but ignores ASCII case.
+fn find_bytes_ignore_ascii_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let m = needle.len();
+    if m == 0 {
+        return Some(0);
+    }
+    if m > haystack.len() {
+        return None;
+    }
+    if m < BMH_MIN_NEEDLE {
+        return haystack
+            .windows(m)
+            .position(|w| w.eq_ignore_ascii_case(needle));
+    }
+    find_bmh(
+        haystack,
+        needle,
+        |b| b.to_ascii_lowercase(),
+        |a, b| a.eq_ignore_ascii_case(b),
+    )
+}
+
+/// Finds first occurence of byte *b* in *haystack*.
+#[inline]
+fn find_byte(haystack: &[u8], b: u8) -> Option<usize> {
+    if haystack.is_empty() {
+        return None;
+    }
+    let base = haystack.as_ptr();
+    let found = unsafe { libc::memchr(base as *const c_void, c_int::from(b), haystack.len()) };
+    if found.is_null() {
+        None
+    } else {
+        Some(found as usize - base as usize)
+    }
+}
+
+/// Boyer–Moore–Horspool search; bytes are compared after *fold*, which
+/// should agree with *eq*.
+#[inline]
+fn find_bmh<Fold, Eq>(haystack: &[u8], needle: &[u8], fold: Fold, eq: Eq) -> Option<usize>
+where
+    Fold: Fn(u8) -> u8,
+    Eq: Fn(&[u8], &[u8]) -> bool,
+{
+    let m = needle.len();
+    let last = m - 1;
+    let mut shift = [m; 256];
+    for (i, &b) in needle[..last].iter().enumerate() {
+        shift[fold(b) as usize] = last - i;
+    }
+    let needle_last = fold(needle[last]);
+    let mut pos = 0;
+    while pos + m <= haystack.len() {
+        let tail = fold(haystack[pos + last]);
+        if tail == needle_last && eq(&haystack[pos..pos + last], &needle[..last]) {
+            return Some(pos);
+        }
+        pos += shift[tail as usize];
+    }
+    None
+}
@@ -0,0 +1,112 @@
+use std::cmp;
+
+use super::{Chain, Flavor, Local};
+
+/// Iterator over parts of chain separated by needle, see
+/// `Chain::split_on` and `Chain::splitn_on`.
+pub struct Split<'a, 'src: 'a, F: Flavor = Local> {
+    chain: &'a Chain<'src, F>,
+    needle: &'a [u8],
+    pos: usize,
+    // Number of parts left to return, None for unlimited
+    left: Option<usize>,
+}
+
+impl<'a, 'src, F: Flavor> Iterator for Split<'a, 'src, F> {
+    type Item = Chain<'src, F>;
+
+    fn next(&mut self) -> Option<Chain<'src, F>> {
+        match self.left {
+            Some(0) => return None,
+            Some(ref mut n) => *n -= 1,
+            None => {}
+        }
+        let found = if self.left == Some(0) || self.needle.is_empty() {
+            None
+        } else {
+            self.chain.find_from(self.pos, self.needle)
+        };
+        match found {
+            Some(offs) => {
+                let part = self.chain.share_range(self.pos, offs);
+                self.pos = offs + self.needle.len();
+                Some(part)
+            }
+            None => {
+                self.left = Some(0);
+                Some(self.chain.share_range(self.pos, self.chain.len()))
+            }
+        }
+    }
+}
+
+impl<'src, F: Flavor> Chain<'src, F> {
+    /// Returns iterator over parts of chain separated by *needle*. Parts
+    /// are new chains sharing data holders with this one, no data copy
+    /// happens. As with `str::split`, *n* separators give *n + 1* parts;
+    /// empty *needle* never matches.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("a\nbb\n".as_bytes());
+    /// chain.append_slice("ccc".as_bytes());
+    /// let parts: Vec<Vec<u8>> = chain
+    ///     .split_on(b"\n")
+    ///     .map(|p| p.copy_bytes_from(0, p.len()))
+    ///     .collect();
+    /// assert_eq!(parts, vec![b"a".to_vec(), b"bb".to_vec(), b"ccc".to_vec()]);
+    /// ```
+    pub fn split_on<'a>(&'a self, needle: &'a [u8]) -> Split<'a, 'src, F> {
+        Split {
+            chain: self,
+            needle,
+            pos: 0,
+            left: None,
+        }
+    }
+
+    /// Same as `split_on`, but returns at most *n* parts, the last one
+    /// containing the rest of chain.
+    /// # Example
+    /// ```
+    /// use chainbuf::Chain;
+    /// let mut chain = Chain::new();
+    /// chain.append_bytes("key: value: more".as_bytes());
+    /// let mut parts = chain.splitn_on(2, b": ");
+    /// assert_eq!(parts.next().unwrap().len(), 3);
+    /// assert_eq!(parts.next().unwrap().len(), 11);
+    /// assert!(parts.next().is_none());
+    /// ```
+    pub fn splitn_on<'a>(&'a self, n: usize, needle: &'a [u8]) -> Split<'a, 'src, F> {
+        Split {
+            chain: self,
+            needle,
+            pos: 0,
+            left: Some(n),
+        }
+    }
+
+    /// Returns new chain with data from *from* to *to*, sharing data
+    /// holders with this one.
+    pub(super) fn share_range(&self, from: usize, to: usize) -> Chain<'src, F> {
+        let mut res = Chain::default();
+        let (idx, mut skip) = match self.head.locate(from) {
+            Some(found) if from < to => found,
+            _ => return res,
+        };
+        let mut left = to - from;
+        for n in self.head.iter_from(idx) {
+            let mut node = n.clone();
+            node.start += skip;
+            node.end = node.start + cmp::min(node.end - node.start, left);
+            left -= node.size();
+            skip = 0;
+            res.add_node_tail(node);
+            if left == 0 {
+                break;
+            }
+        }
+        res
+    }
+}
@@ -0,0 +1,336 @@
+//! Framing of byte streams on top of `Chain`.
+//!
+//! `Decoder` cuts frames out of chain with received data and `Encoder`
+//! appends frames to chain with data to send. `Framed` drives them over
+//! any `Source` and `Sink`: std `Read`/`Write` implementors or raw file
+//! descriptors (`Fd`), read with `Chain::read_from_fd` and written with
+//! `Chain::write_to_fd`.
+//! # Example
+//! ```
+//! use chainbuf::codec::{Framed, LengthDelimitedCodec};
+//! use std::io::Cursor;
+//! let mut out = Vec::new();
+//! let mut framed = Framed::new(&mut out, LengthDelimitedCodec::new());
+//! framed.send("hello".as_bytes()).unwrap();
+//! assert_eq!(out, b"\0\0\0\x05hello".to_vec());
+//! let mut framed = Framed::new(Cursor::new(out), LengthDelimitedCodec::new());
+//! let mut frame = framed.read_frame().unwrap().unwrap();
+//! assert_eq!(frame.pullup_all().unwrap(), "hello".as_bytes());
+//! assert!(framed.read_frame().unwrap().is_none());
+//! ```
+
+use std::io;
+use std::io::{BufRead, Read, Write};
+
+#[cfg(feature = "nix")]
+use std::os::unix::io::RawFd;
+
+use crate::chainbuf::{Chain, Flavor, Local};
+
+mod delimiter;
+mod length_delimited;
+
+pub use self::delimiter::DelimiterCodec;
+pub use self::length_delimited::LengthDelimitedCodec;
+
+// Size of data `Framed` tries to read at once
+const READ_SIZE: usize = 8 * 1024;
+// Default limit of frame length for ready-made codecs
+const MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+// Error for malformed data received from peer
+pub(crate) fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Decodes frames from chain with received data.
+/// # Example
+/// Decoder of frames ending with zero byte, that can fail with its own
+/// error type:
+/// ```
+/// use chainbuf::codec::Decoder;
+/// use chainbuf::Chain;
+/// use std::io;
+/// #[derive(Debug)]
+/// enum Error {
+///     Empty,
+///     Io(io::Error),
+/// }
+/// impl From<io::Error> for Error {
+///     fn from(e: io::Error) -> Error {
+///         Error::Io(e)
+///     }
+/// }
+/// struct ZeroTerminated;
+/// impl<'src> Decoder<'src> for ZeroTerminated {
+///     type Item = Chain<'src>;
+///     type Error = Error;
+///     fn decode(&mut self, src: &mut Chain<'src>) -> Result<Option<Chain<'src>>, Error> {
+///         match src.find_byte(0) {
+///             Some(0) => Err(Error::Empty),
+///             Some(offs) => {
+///                 let frame = src.split_to(offs);
+///                 src.drain(1);
+///                 Ok(Some(frame))
+///             }
+///             None => Ok(None),
+///         }
+///     }
+/// }
+/// let mut chain = Chain::new();
+/// chain.append_bytes("ab\0\0".as_bytes());
+/// assert_eq!(ZeroTerminated.decode(&mut chain).unwrap().unwrap().len(), 2);
+/// assert!(matches!(ZeroTerminated.decode(&mut chain), Err(Error::Empty)));
+/// ```
+pub trait Decoder<'src, F: Flavor = Local> {
+    /// Type of decoded frames.
+    type Item;
+    /// Type of decoding errors. I/O errors of `Framed` are converted to it.
+    type Error: From<io::Error>;
+
+    /// Removes one frame from the beginning of *src* and returns it.
+    /// Returns `Ok(None)` if *src* does not contain complete frame yet;
+    /// data of incomplete frame should be left in *src* then.
+    fn decode(&mut self, src: &mut Chain<'src, F>) -> Result<Option<Self::Item>, Self::Error>;
+
+    /// Same as `decode`, but called when no more data is going to come.
+    /// By default it fails with `UnexpectedEof` if *src* has data, which
+    /// is not a complete frame.
+    fn decode_eof(&mut self, src: &mut Chain<'src, F>) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "incomplete frame at the end of stream",
+            )
+            .into()),
+        }
+    }
+}
+
+/// Encodes frames of type *Item* into chain with data to send.
+pub trait Encoder<'src, Item, F: Flavor = Local> {
+    /// Type of encoding errors. I/O errors of `Framed` are converted to it.
+    type Error: From<io::Error>;
+
+    /// Appends encoded *item* to the end of *dst*.
+    fn encode(&mut self, item: Item, dst: &mut Chain<'src, F>) -> Result<(), Self::Error>;
+}
+
+/// Byte stream, that `Framed` reads data from.
+pub trait Source {
+    /// Reads at most *max* bytes to the end of *dst* and returns number
+    /// of bytes read, 0 meaning end of stream.
+    fn read_chain<F: Flavor>(&mut self, dst: &mut Chain<'_, F>, max: usize) -> io::Result<usize>;
+}
+
+/// Byte stream, that `Framed` writes data to.
+pub trait Sink {
+    /// Writes data from the beginning of *src*, drains written bytes and
+    /// returns their number.
+    fn write_chain<F: Flavor>(&mut self, src: &mut Chain<'_, F>) -> io::Result<usize>;
+
+    /// Flushes data buffered by sink itself, if any.
+    fn flush_chain(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Data is read into the room left in the last node of chain, if any, so
+/// slow stream doesn't leave a node per read; new node is only allocated
+/// when the last one is full.
+impl<R: Read> Source for R {
+    fn read_chain<F: Flavor>(&mut self, dst: &mut Chain<'_, F>, max: usize) -> io::Result<usize> {
+        dst.read_into_tail(max, |buf| loop {
+            match self.read(buf) {
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                res => return res,
+            }
+        })
+    }
+}
+
+/// Data is written node by node.
+impl<W: Write> Sink for W {
+    fn write_chain<F: Flavor>(&mut self, src: &mut Chain<'_, F>) -> io::Result<usize> {
+        let n = loop {
+            match self.write(src.fill_buf()?) {
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                res => break res?,
+            }
+        };
+        src.consume(n);
+        Ok(n)
+    }
+
+    fn flush_chain(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+/// Raw file descriptor as `Source` and `Sink`. Data is read with
+/// `Chain::read_from_fd` and written with `Chain::write_to_fd`, so
+/// writing takes one `writev` or `sendfile` call. Descriptor is not
+/// closed on drop.
+#[cfg(feature = "nix")]
+#[derive(Debug, Clone, Copy)]
+pub struct Fd(pub RawFd);
+
+#[cfg(feature = "nix")]
+impl Source for Fd {
+    fn read_chain<F: Flavor>(&mut self, dst: &mut Chain<'_, F>, max: usize) -> io::Result<usize> {
+        dst.read_from_fd(self.0, max).map_err(from_nix)
+    }
+}
+
+#[cfg(feature = "nix")]
+impl Sink for Fd {
+    fn write_chain<F: Flavor>(&mut self, src: &mut Chain<'_, F>) -> io::Result<usize> {
+        src.write_to_fd(self.0, None, None).map_err(from_nix)
+    }
+}
+
+// `io::Error::other` needs Rust 1.74
+#[cfg(feature = "nix")]
+#[allow(unknown_lints, clippy::io_other_error)]
+fn from_nix(e: nix::Error) -> io::Error {
+    match e {
+        nix::Error::Sys(errno) => errno.into(),
+        e => io::Error::new(io::ErrorKind::Other, e),
+    }
+}
+
+/// Stream of frames on top of byte stream *T*, decoded and encoded with
+/// codec *C*. Received data and encoded frames are buffered in chains,
+/// so non-blocking streams are supported: `WouldBlock` error is returned
+/// as is and the call can be repeated later.
+pub struct Framed<T, C, F: Flavor = Local> {
+    io: T,
+    codec: C,
+    rd: Chain<'static, F>,
+    wr: Chain<'static, F>,
+    eof: bool,
+}
+
+impl<T, C> Framed<T, C> {
+    /// Creates framed stream on top of *io*.
+    pub fn new(io: T, codec: C) -> Framed<T, C> {
+        Framed::with_buffer(io, codec, Chain::new())
+    }
+}
+
+impl<T, C, F: Flavor> Framed<T, C, F> {
+    /// Creates framed stream on top of *io* with data already read from
+    /// it in *rd*. Flavor of *rd* is used for all buffers.
+    pub fn with_buffer(io: T, codec: C, rd: Chain<'static, F>) -> Framed<T, C, F> {
+        Framed {
+            io,
+            codec,
+            rd,
+            wr: Chain::default(),
+            eof: false,
+        }
+    }
+
+    /// Returns reference to underlying byte stream.
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns mutable reference to underlying byte stream.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Returns reference to codec.
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns mutable reference to codec.
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Returns chain with received data, that is not decoded yet.
+    pub fn read_buffer(&self) -> &Chain<'static, F> {
+        &self.rd
+    }
+
+    /// Returns chain with encoded data, that is not written yet.
+    pub fn write_buffer(&self) -> &Chain<'static, F> {
+        &self.wr
+    }
+
+    /// Destroys framed stream and returns underlying byte stream, codec
+    /// and received data, that is not decoded yet.
+    pub fn into_parts(self) -> (T, C, Chain<'static, F>) {
+        (self.io, self.codec, self.rd)
+    }
+
+    /// Returns next frame, reading more data from stream when needed, or
+    /// None when stream is over.
+    pub fn read_frame(&mut self) -> Result<Option<C::Item>, C::Error>
+    where
+        T: Source,
+        C: Decoder<'static, F>,
+    {
+        loop {
+            if self.eof {
+                return self.codec.decode_eof(&mut self.rd);
+            }
+            if let Some(frame) = self.codec.decode(&mut self.rd)? {
+                return Ok(Some(frame));
+            }
+            if self.io.read_chain(&mut self.rd, READ_SIZE)? == 0 {
+                self.eof = true;
+            }
+        }
+    }
+
+    /// Encodes *item* into write buffer, without writing it to stream.
+    /// See `flush`.
+    pub fn write_frame<I>(&mut self, item: I) -> Result<(), C::Error>
+    where
+        C: Encoder<'static, I, F>,
+    {
+        self.codec.encode(item, &mut self.wr)
+    }
+
+    /// Writes all buffered frames to stream.
+    pub fn flush(&mut self) -> io::Result<()>
+    where
+        T: Sink,
+    {
+        while !self.wr.is_empty() {
+            if self.io.write_chain(&mut self.wr)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write buffered frames",
+                ));
+            }
+        }
+        self.io.flush_chain()
+    }
+
+    /// Encodes *item* and writes it to stream with all frames buffered
+    /// before.
+    pub fn send<I>(&mut self, item: I) -> Result<(), C::Error>
+    where
+        T: Sink,
+        C: Encoder<'static, I, F>,
+    {
+        self.write_frame(item)?;
+        Ok(self.flush()?)
+    }
+}
+
+/// Iterates over frames until the end of stream, see `Framed::read_frame`.
+impl<T: Source, C: Decoder<'static, F>, F: Flavor> Iterator for Framed<T, C, F> {
+    type Item = Result<C::Item, C::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
@@ -0,0 +1,123 @@
+use std::io;
+
+use super::{Decoder, Encoder, MAX_FRAME_LENGTH};
+use crate::chainbuf::{Chain, Flavor, Searcher};
+
+/// Codec for frames terminated with delimiter, like `\r\n` or `\0`.
+/// Decoded frames are chains without delimiter, sharing data with
+/// received one.
+///
+/// Codec remembers how far received data was scanned for delimiter, so
+/// each byte is looked at once, however small the pieces data comes in.
+/// Hence codec instance should be used with one stream only.
+/// # Example
+/// ```
+/// use chainbuf::codec::{Decoder, DelimiterCodec};
+/// use chainbuf::Chain;
+/// let mut codec = DelimiterCodec::new(b"\r\n").max_frame_length(16);
+/// let mut chain = Chain::new();
+/// chain.append_bytes("PING\r".as_bytes());
+/// assert!(codec.decode(&mut chain).unwrap().is_none());
+/// chain.append_bytes("\nQUIT".as_bytes());
+/// let mut frame = codec.decode(&mut chain).unwrap().unwrap();
+/// assert_eq!(frame.pullup_all().unwrap(), "PING".as_bytes());
+/// assert_eq!(chain.len(), 4);
+/// ```
+pub struct DelimiterCodec {
+    delim: Vec<u8>,
+    searcher: Searcher,
+    max_frame_length: usize,
+}
+
+impl DelimiterCodec {
+    /// Creates codec for frames terminated with *delim* with maximum frame
+    /// length of 8 MiB.
+    /// # Panics
+    /// Panics if *delim* is empty.
+    pub fn new(delim: &[u8]) -> DelimiterCodec {
+        assert!(!delim.is_empty(), "delimiter should not be empty");
+        DelimiterCodec {
+            delim: delim.to_vec(),
+            searcher: Searcher::new(delim),
+            max_frame_length: MAX_FRAME_LENGTH,
+        }
+    }
+
+    /// Sets maximum length of frame without delimiter. Decoding fails with
+    /// `InvalidData` as soon as there is more data without delimiter, so
+    /// peer can't make receiver buffer data forever. Encoding of longer
+    /// frame fails with `InvalidInput`.
+    pub fn max_frame_length(mut self, max: usize) -> DelimiterCodec {
+        self.max_frame_length = max;
+        self
+    }
+
+    fn check_length(&self, len: usize, kind: io::ErrorKind) -> io::Result<()> {
+        if len > self.max_frame_length {
+            Err(io::Error::new(
+                kind,
+                "frame is longer than max_frame_length",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'src, F: Flavor> Decoder<'src, F> for DelimiterCodec {
+    type Item = Chain<'src, F>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut Chain<'src, F>) -> io::Result<Option<Chain<'src, F>>> {
+        match self.searcher.next(src) {
+            Some(offs) => {
+                self.check_length(offs, io::ErrorKind::InvalidData)?;
+                let frame = src.split_to(offs);
+                src.drain(self.delim.len());
+                Ok(Some(frame))
+            }
+            None => {
+                // Delimiter may start in the last `delim.len() - 1` bytes
+                let pending = (src.len() + 1).saturating_sub(self.delim.len());
+                self.check_length(pending, io::ErrorKind::InvalidData)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Data left after the last delimiter is returned as the last frame.
+    fn decode_eof(&mut self, src: &mut Chain<'src, F>) -> io::Result<Option<Chain<'src, F>>> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            None => {
+                self.check_length(src.len(), io::ErrorKind::InvalidData)?;
+                let len = src.len();
+                Ok(Some(src.split_to(len)))
+            }
+        }
+    }
+}
+
+/// Frame data is moved to destination chain, no data copy happens.
+impl<'src, F: Flavor> Encoder<'src, Chain<'src, F>, F> for DelimiterCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Chain<'src, F>, dst: &mut Chain<'src, F>) -> io::Result<()> {
+        self.check_length(item.len(), io::ErrorKind::InvalidInput)?;
+        dst.concat(item);
+        dst.append_bytes(&self.delim);
+        Ok(())
+    }
+}
+
+impl<'a, 'src, F: Flavor> Encoder<'src, &'a [u8], F> for DelimiterCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: &'a [u8], dst: &mut Chain<'src, F>) -> io::Result<()> {
+        self.check_length(item.len(), io::ErrorKind::InvalidInput)?;
+        dst.append_bytes(item);
+        dst.append_bytes(&self.delim);
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use std::io;
+
+use super::{Decoder, Encoder, MAX_FRAME_LENGTH};
+use crate::chainbuf::{Chain, Flavor};
+
+/// Codec for frames prefixed with their length. Length field is 4 bytes
+/// big-endian by default and doesn't include itself. Decoded frames are
+/// chains sharing data with received one.
+/// # Example
+/// ```
+/// use chainbuf::codec::{Decoder, Encoder, LengthDelimitedCodec};
+/// use chainbuf::Chain;
+/// let mut codec = LengthDelimitedCodec::new()
+///     .length_field_length(2)
+///     .little_endian()
+///     .max_frame_length(1024);
+/// let mut chain = Chain::new();
+/// codec.encode("hello".as_bytes(), &mut chain).unwrap();
+/// assert_eq!(chain.peek(3).unwrap(), b"\x05\0h".as_ref());
+/// let mut frame = codec.decode(&mut chain).unwrap().unwrap();
+/// assert_eq!(frame.pullup_all().unwrap(), "hello".as_bytes());
+/// assert!(chain.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct LengthDelimitedCodec {
+    length_size: usize,
+    little_endian: bool,
+    max_frame_length: usize,
+}
+
+impl LengthDelimitedCodec {
+    /// Creates codec with 4 bytes big-endian length field and maximum
+    /// frame length of 8 MiB.
+    pub fn new() -> LengthDelimitedCodec {
+        LengthDelimitedCodec {
+            length_size: 4,
+            little_endian: false,
+            max_frame_length: MAX_FRAME_LENGTH,
+        }
+    }
+
+    /// Sets size of length field, which should be 1, 2, 4 or 8 bytes.
+    /// # Panics
+    /// Panics if *size* is not supported.
+    pub fn length_field_length(mut self, size: usize) -> LengthDelimitedCodec {
+        assert!(
+            [1, 2, 4, 8].contains(&size),
+            "unsupported length field size: {}",
+            size
+        );
+        self.length_size = size;
+        self
+    }
+
+    /// Makes length field little-endian.
+    pub fn little_endian(mut self) -> LengthDelimitedCodec {
+        self.little_endian = true;
+        self
+    }
+
+    /// Sets maximum length of frame. Decoding fails with `InvalidData`
+    /// as soon as length field of longer frame is received, and encoding
+    /// of such frame fails with `InvalidInput`.
+    pub fn max_frame_length(mut self, max: usize) -> LengthDelimitedCodec {
+        self.max_frame_length = max;
+        self
+    }
+
+    fn peek_length<F: Flavor>(&self, src: &Chain<'_, F>) -> Option<u64> {
+        match (self.length_size, self.little_endian) {
+            (1, _) => src.peek_u8().map(u64::from),
+            (2, false) => src.peek_u16_be().map(u64::from),
+            (2, true) => src.peek_u16_le().map(u64::from),
+            (4, false) => src.peek_u32_be().map(u64::from),
+            (4, true) => src.peek_u32_le().map(u64::from),
+            (8, false) => src.peek_u64_be(),
+            _ => src.peek_u64_le(),
+        }
+    }
+
+    /// Checks that frame of *len* bytes can be sent and appends its length
+    /// field to *dst*.
+    fn put_length<F: Flavor>(&self, len: usize, dst: &mut Chain<'_, F>) -> io::Result<()> {
+        let fits = self.length_size == 8 || (len as u64) >> (8 * self.length_size) == 0;
+        if len > self.max_frame_length || !fits {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame is longer than max_frame_length",
+            ));
+        }
+        match (self.length_size, self.little_endian) {
+            (1, _) => dst.put_u8(len as u8),
+            (2, false) => dst.put_u16_be(len as u16),
+            (2, true) => dst.put_u16_le(len as u16),
+            (4, false) => dst.put_u32_be(len as u32),
+            (4, true) => dst.put_u32_le(len as u32),
+            (8, false) => dst.put_u64_be(len as u64),
+            _ => dst.put_u64_le(len as u64),
+        }
+        Ok(())
+    }
+}
+
+impl Default for LengthDelimitedCodec {
+    fn default() -> LengthDelimitedCodec {
+        LengthDelimitedCodec::new()
+    }
+}
+
+impl<'src, F: Flavor> Decoder<'src, F> for LengthDelimitedCodec {
+    type Item = Chain<'src, F>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut Chain<'src, F>) -> io::Result<Option<Chain<'src, F>>> {
+        let len = match self.peek_length(src) {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        if len > self.max_frame_length as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "frame is longer than max_frame_length",
+            ));
+        }
+        let len = len as usize;
+        if src.len() - self.length_size < len {
+            return Ok(None);
+        }
+        src.drain(self.length_size);
+        Ok(Some(src.split_to(len)))
+    }
+}
+
+/// Frame data is moved to destination chain, no data copy happens.
+impl<'src, F: Flavor> Encoder<'src, Chain<'src, F>, F> for LengthDelimitedCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Chain<'src, F>, dst: &mut Chain<'src, F>) -> io::Result<()> {
+        self.put_length(item.len(), dst)?;
+        dst.concat(item);
+        Ok(())
+    }
+}
+
+impl<'a, 'src, F: Flavor> Encoder<'src, &'a [u8], F> for LengthDelimitedCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: &'a [u8], dst: &mut Chain<'src, F>) -> io::Result<()> {
+        self.put_length(item.len(), dst)?;
+        dst.append_bytes(item);
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+//! Building blocks of HTTP/1.1 on top of `Chain` and `codec` traits.
+
+mod chunked;
+mod head;
+
+pub use self::chunked::{Chunk, ChunkedDecoder, ChunkedEncoder};
+pub use self::head::{Head, HeadCodec};
@@ -0,0 +1,241 @@
+use std::cmp;
+use std::io;
+use std::io::Write;
+
+use crate::chainbuf::{Chain, Flavor, Local};
+use crate::codec::{invalid, Decoder, Encoder};
+
+// Limits of chunk size line (with extensions) and trailer section
+const MAX_SIZE_LINE: usize = 1024;
+const MAX_TRAILERS: usize = 16 * 1024;
+
+/// Piece of chunked body, see `ChunkedDecoder` and `ChunkedEncoder`.
+pub enum Chunk<'src, F: Flavor = Local> {
+    /// Chunk payload.
+    Data(Chain<'src, F>),
+    /// End of body with trailer section: raw header lines, each ending
+    /// with CRLF, without the final empty line. Empty if there are no
+    /// trailers.
+    End(Chain<'src, F>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Size,
+    // Bytes of chunk payload left to receive
+    Data(usize),
+    DataEnd,
+    Trailers,
+    Done,
+}
+
+/// Decoder of body in chunked transfer-encoding. Payload is returned as
+/// chains sharing data with received one, no data copy happens. Payload
+/// is returned as soon as it's received, so one chunk may come in
+/// several `Chunk::Data` pieces. Body ends with `Chunk::End`; after that
+/// decoder returns `Ok(None)` and leaves following data (e.g. next
+/// message) in chain, until `reset`.
+/// # Example
+/// ```
+/// use chainbuf::codec::Decoder;
+/// use chainbuf::http::{Chunk, ChunkedDecoder};
+/// use chainbuf::Chain;
+/// let mut decoder = ChunkedDecoder::new();
+/// let mut chain = Chain::new();
+/// chain.append_bytes("5;ext=1\r\nhello\r\n0\r\nExpires: 0\r\n\r\n".as_bytes());
+/// match decoder.decode(&mut chain).unwrap() {
+///     Some(Chunk::Data(mut data)) => assert_eq!(data.pullup_all().unwrap(), "hello".as_bytes()),
+///     _ => panic!("expected data"),
+/// }
+/// match decoder.decode(&mut chain).unwrap() {
+///     Some(Chunk::End(mut trailers)) => {
+///         assert_eq!(trailers.pullup_all().unwrap(), "Expires: 0\r\n".as_bytes())
+///     }
+///     _ => panic!("expected end"),
+/// }
+/// assert!(decoder.is_done());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChunkedDecoder {
+    state: State,
+}
+
+impl ChunkedDecoder {
+    /// Creates decoder expecting the first chunk.
+    pub fn new() -> ChunkedDecoder {
+        ChunkedDecoder { state: State::Size }
+    }
+
+    /// Returns true if the whole body has been decoded.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Resets decoder to decode next body.
+    pub fn reset(&mut self) {
+        self.state = State::Size;
+    }
+}
+
+impl Default for ChunkedDecoder {
+    fn default() -> ChunkedDecoder {
+        ChunkedDecoder::new()
+    }
+}
+
+impl<'src, F: Flavor> Decoder<'src, F> for ChunkedDecoder {
+    type Item = Chunk<'src, F>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut Chain<'src, F>) -> io::Result<Option<Chunk<'src, F>>> {
+        loop {
+            match self.state {
+                State::Size => {
+                    let nl = match src.find_byte(b'\n') {
+                        Some(nl) if nl <= MAX_SIZE_LINE => nl,
+                        None if src.len() <= MAX_SIZE_LINE => return Ok(None),
+                        _ => return Err(invalid("chunk size line is too long")),
+                    };
+                    let size = match src.peek(nl) {
+                        Some(line) => parse_size(&line)?,
+                        None => return Err(invalid("empty chunk size line")),
+                    };
+                    src.drain(nl + 1);
+                    self.state = if size == 0 {
+                        State::Trailers
+                    } else {
+                        State::Data(size)
+                    };
+                }
+                State::Data(left) => {
+                    if src.is_empty() {
+                        return Ok(None);
+                    }
+                    let n = cmp::min(left, src.len());
+                    self.state = if n == left {
+                        State::DataEnd
+                    } else {
+                        State::Data(left - n)
+                    };
+                    return Ok(Some(Chunk::Data(src.split_to(n))));
+                }
+                State::DataEnd => match src.peek(2) {
+                    None => return Ok(None),
+                    Some(crlf) if &crlf[..] == b"\r\n" => {
+                        src.drain(2);
+                        self.state = State::Size;
+                    }
+                    Some(_) => return Err(invalid("chunk payload is not followed by CRLF")),
+                },
+                State::Trailers => {
+                    let end = if src.peek(2).as_deref() == Some(b"\r\n") {
+                        Some(0)
+                    } else {
+                        src.find(b"\r\n\r\n").map(|offs| offs + 2)
+                    };
+                    return match end {
+                        Some(end) if end <= MAX_TRAILERS => {
+                            let trailers = src.split_to(end);
+                            src.drain(2);
+                            self.state = State::Done;
+                            Ok(Some(Chunk::End(trailers)))
+                        }
+                        None if src.len() <= MAX_TRAILERS => Ok(None),
+                        _ => Err(invalid("trailer section is too long")),
+                    };
+                }
+                State::Done => return Ok(None),
+            }
+        }
+    }
+
+    /// Fails with `UnexpectedEof` if body is not complete.
+    fn decode_eof(&mut self, src: &mut Chain<'src, F>) -> io::Result<Option<Chunk<'src, F>>> {
+        match self.decode(src)? {
+            Some(chunk) => Ok(Some(chunk)),
+            None if self.is_done() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "incomplete chunked body",
+            )),
+        }
+    }
+}
+
+/// Encoder of body in chunked transfer-encoding. Size lines and CRLFs are
+/// added around payload chains, payload itself is not copied. Empty
+/// `Chunk::Data` is skipped, as empty chunk ends body; use `Chunk::End`
+/// for this.
+/// # Example
+/// ```
+/// use chainbuf::codec::Encoder;
+/// use chainbuf::http::{Chunk, ChunkedEncoder};
+/// use chainbuf::Chain;
+/// let mut dst = Chain::new();
+/// let mut payload = Chain::new();
+/// payload.append_slice("0123456789abcdef!".as_bytes());
+/// ChunkedEncoder.encode(Chunk::Data(payload), &mut dst).unwrap();
+/// ChunkedEncoder.encode(Chunk::End(Chain::new()), &mut dst).unwrap();
+/// assert_eq!(
+///     dst.pullup_all().unwrap(),
+///     "11\r\n0123456789abcdef!\r\n0\r\n\r\n".as_bytes()
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChunkedEncoder;
+
+impl<'src, F: Flavor> Encoder<'src, Chunk<'src, F>, F> for ChunkedEncoder {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Chunk<'src, F>, dst: &mut Chain<'src, F>) -> io::Result<()> {
+        match item {
+            Chunk::Data(data) => {
+                if !data.is_empty() {
+                    write!(dst, "{:x}\r\n", data.len())?;
+                    dst.concat(data);
+                    dst.append_bytes(b"\r\n");
+                }
+            }
+            Chunk::End(trailers) => {
+                dst.append_bytes(b"0\r\n");
+                dst.concat(trailers);
+                dst.append_bytes(b"\r\n");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Encodes *item* as one chunk, copying it.
+impl<'a, 'src, F: Flavor> Encoder<'src, &'a [u8], F> for ChunkedEncoder {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: &'a [u8], dst: &mut Chain<'src, F>) -> io::Result<()> {
+        if !item.is_empty() {
+            write!(dst, "{:x}\r\n", item.len())?;
+            dst.append_bytes(item);
+            dst.append_bytes(b"\r\n");
+        }
+        Ok(())
+    }
+}
+
+/// Parses chunk size line without LF, ignoring extensions.
+fn parse_size(line: &[u8]) -> io::Result<usize> {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let digits = line.split(|&b| b == b';').next().unwrap_or(line);
+    let end = digits
+        .iter()
+        .rposition(|&b| b != b' ' && b != b'\t')
+        .map_or(0, |p| p + 1);
+    if end == 0 {
+        return Err(invalid("chunk size is missing"));
+    }
+    digits[..end]
+        .iter()
+        .try_fold(0usize, |size, &b| {
+            let d = (b as char).to_digit(16)?;
+            size.checked_mul(16)?.checked_add(d as usize)
+        })
+        .ok_or_else(|| invalid("invalid chunk size"))
+}
@@ -0,0 +1,432 @@
+use std::borrow::Cow;
+use std::io;
+use std::io::Write;
+use std::ops::Range;
+
+use crate::chainbuf::{Chain, Flavor, Local};
+use crate::codec::{invalid, Decoder, Encoder};
+
+// Default limit of head size
+const MAX_HEAD_LENGTH: usize = 64 * 1024;
+
+// Offsets of header line (with CRLF), name and value inside head
+#[derive(Debug, Clone)]
+struct HeaderPos {
+    line: Range<usize>,
+    name: Range<usize>,
+    value: Range<usize>,
+}
+
+/// Head of HTTP/1.x request or response: start line and headers.
+///
+/// Head keeps its data in chain (see `raw`) and only offsets of tokens
+/// inside it, so parsed head shares data with received one and accessors
+/// return slices, which are borrowed unless token lies across nodes.
+/// # Example
+/// ```
+/// use chainbuf::codec::Decoder;
+/// use chainbuf::http::HeadCodec;
+/// use chainbuf::Chain;
+/// let mut chain = Chain::new();
+/// chain.append_bytes("GET /index.html HTTP/1.1\r\nHost: exam".as_bytes());
+/// chain.append_slice("ple.com\r\nAccept:  */* \r\n\r\nbody".as_bytes());
+/// let head = HeadCodec::new().decode(&mut chain).unwrap().unwrap();
+/// assert_eq!(&head.method().unwrap()[..], b"GET");
+/// assert_eq!(&head.target().unwrap()[..], b"/index.html");
+/// assert_eq!(&head.version()[..], b"HTTP/1.1");
+/// assert_eq!(&head.header(b"host").unwrap()[..], b"example.com");
+/// assert_eq!(&head.header(b"accept").unwrap()[..], b"*/*");
+/// assert_eq!(chain.len(), 4);
+/// ```
+pub struct Head<'src, F: Flavor = Local> {
+    raw: Chain<'src, F>,
+    // Method, target and version of request or version, status and
+    // reason of response
+    start: [Range<usize>; 3],
+    status: Option<u16>,
+    headers: Vec<HeaderPos>,
+}
+
+impl<'src, F: Flavor> Head<'src, F> {
+    /// Creates HTTP/1.1 request head without headers.
+    /// # Panics
+    /// Panics if *method* or *target* are empty or contain whitespace or
+    /// control characters.
+    pub fn request(method: &str, target: &str) -> Head<'src, F> {
+        assert!(is_token(method.as_bytes()), "invalid method");
+        assert!(is_target(target.as_bytes()), "invalid request target");
+        let mut raw = Chain::default();
+        // infailable: writing to chain
+        write!(raw, "{} {} HTTP/1.1\r\n", method, target).unwrap();
+        let m = method.len();
+        let t = m + 1 + target.len();
+        Head {
+            start: [0..m, m + 1..t, t + 1..raw.len() - 2],
+            raw,
+            status: None,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Creates HTTP/1.1 response head without headers.
+    /// # Panics
+    /// Panics if *status* has not three digits or *reason* contains
+    /// control characters.
+    pub fn response(status: u16, reason: &str) -> Head<'src, F> {
+        assert!((100..1000).contains(&status), "invalid status");
+        assert!(is_text(reason.as_bytes()), "invalid reason phrase");
+        let mut raw = Chain::default();
+        // infailable: writing to chain
+        write!(raw, "HTTP/1.1 {} {}\r\n", status, reason).unwrap();
+        Head {
+            start: [0..8, 9..12, 13..raw.len() - 2],
+            raw,
+            status: Some(status),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Returns true if it's a response head.
+    pub fn is_response(&self) -> bool {
+        self.status.is_some()
+    }
+
+    /// Returns method of request, None for response.
+    pub fn method(&self) -> Option<Cow<'_, [u8]>> {
+        self.request_token(0)
+    }
+
+    /// Returns target of request, None for response.
+    pub fn target(&self) -> Option<Cow<'_, [u8]>> {
+        self.request_token(1)
+    }
+
+    /// Returns status of response, None for request.
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    /// Returns reason phrase of response, None for request.
+    pub fn reason(&self) -> Option<Cow<'_, [u8]>> {
+        self.status.map(|_| self.slice(&self.start[2]))
+    }
+
+    /// Returns protocol version, like `HTTP/1.1`.
+    pub fn version(&self) -> Cow<'_, [u8]> {
+        let idx = if self.is_response() { 0 } else { 2 };
+        self.slice(&self.start[idx])
+    }
+
+    /// Returns value of the first header named *name* (case-insensitive)
+    /// without surrounding whitespace.
+    pub fn header(&self, name: &[u8]) -> Option<Cow<'_, [u8]>> {
+        self.headers
+            .iter()
+            .find(|h| self.slice(&h.name).eq_ignore_ascii_case(name))
+            .map(|h| self.slice(&h.value))
+    }
+
+    /// Returns iterator over names and values of headers in order they
+    /// appear in head.
+    pub fn headers(&self) -> impl Iterator<Item = (Cow<'_, [u8]>, Cow<'_, [u8]>)> {
+        self.headers
+            .iter()
+            .map(move |h| (self.slice(&h.name), self.slice(&h.value)))
+    }
+
+    /// Returns offsets of name and value of *n*-th header inside `raw`.
+    pub fn header_offsets(&self, n: usize) -> Option<(Range<usize>, Range<usize>)> {
+        self.headers
+            .get(n)
+            .map(|h| (h.name.clone(), h.value.clone()))
+    }
+
+    /// Appends header to head. Fails with `InvalidInput` if *name* is not
+    /// a valid token or *value* contains control characters, so header
+    /// can't be used to inject other headers.
+    pub fn append_header(&mut self, name: &[u8], value: &[u8]) -> io::Result<()> {
+        if !is_token(name) || !is_text(value) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid header name or value",
+            ));
+        }
+        let start = self.raw.len();
+        let value_start = start + name.len() + 2;
+        self.raw.append_bytes(name);
+        self.raw.append_bytes(b": ");
+        self.raw.append_bytes(value);
+        self.raw.append_bytes(b"\r\n");
+        self.headers.push(HeaderPos {
+            line: start..self.raw.len(),
+            name: start..start + name.len(),
+            value: value_start..value_start + value.len(),
+        });
+        Ok(())
+    }
+
+    /// Removes all headers named *name* (case-insensitive) and returns
+    /// number of removed ones. Header lines are cut out of `raw`, the rest
+    /// of data is not copied.
+    pub fn remove_header(&mut self, name: &[u8]) -> usize {
+        let mut removed = 0;
+        let mut i = 0;
+        while i < self.headers.len() {
+            if !self.slice(&self.headers[i].name).eq_ignore_ascii_case(name) {
+                i += 1;
+                continue;
+            }
+            let line = self.headers.remove(i).line;
+            self.raw.remove_range(line.clone());
+            for h in self.headers[i..].iter_mut() {
+                for r in [&mut h.line, &mut h.name, &mut h.value].iter_mut() {
+                    r.start -= line.len();
+                    r.end -= line.len();
+                }
+            }
+            removed += 1;
+        }
+        removed
+    }
+
+    /// Returns chain with start line and header lines, each ending with
+    /// CRLF, but without the empty line ending head.
+    pub fn raw(&self) -> &Chain<'src, F> {
+        &self.raw
+    }
+
+    /// Appends serialized head to *dst*. Data of head is shared, not
+    /// copied.
+    pub fn write_to(&self, dst: &mut Chain<'src, F>) {
+        dst.append(&self.raw);
+        dst.append_bytes(b"\r\n");
+    }
+
+    /// Inserts serialized head at the beginning of *dst*, in front of
+    /// body, which is already there.
+    /// # Example
+    /// Body can be a file added with `append_file`, so the whole response
+    /// is written with `write_to_fd` without copying file data:
+    /// ```
+    /// use chainbuf::http::Head;
+    /// use chainbuf::Chain;
+    /// let mut response = Chain::new();
+    /// response.append_bytes("<h1>hi</h1>".as_bytes()); // or append_file
+    /// let mut head = Head::response(200, "OK");
+    /// let len = response.len().to_string();
+    /// head.append_header(b"Content-Length", len.as_bytes()).unwrap();
+    /// head.prepend_to(&mut response);
+    /// assert_eq!(
+    ///     response.pullup_all().unwrap(),
+    ///     "HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\n<h1>hi</h1>".as_bytes()
+    /// );
+    /// ```
+    pub fn prepend_to(&self, dst: &mut Chain<'src, F>) {
+        let mut head = Chain::default();
+        self.write_to(&mut head);
+        dst.insert_chain(0, head);
+    }
+
+    /// Destroys head and returns it serialized.
+    pub fn into_chain(self) -> Chain<'src, F> {
+        let mut res = self.raw;
+        res.append_bytes(b"\r\n");
+        res
+    }
+
+    /// Parses *raw*, containing lines of head, each ending with CRLF.
+    fn parse(raw: Chain<'src, F>) -> io::Result<Head<'src, F>> {
+        let mut lines = Vec::new();
+        let mut pos = 0;
+        while let Some(end) = raw.find_from(pos, b"\r\n") {
+            lines.push(pos..end);
+            pos = end + 2;
+        }
+        let mut lines = lines.into_iter();
+        // infailable: raw has at least one line
+        let first = lines.next().unwrap();
+        let line = raw.peek_from(first.start, first.len()).unwrap_or_default();
+        let (start, status) = parse_start_line(&line, first.start)?;
+        let headers = lines
+            .map(|l| parse_header(&raw.peek_from(l.start, l.len()).unwrap_or_default(), l))
+            .collect::<io::Result<_>>()?;
+        Ok(Head {
+            raw,
+            start,
+            status,
+            headers,
+        })
+    }
+
+    fn request_token(&self, idx: usize) -> Option<Cow<'_, [u8]>> {
+        match self.status {
+            Some(_) => None,
+            None => Some(self.slice(&self.start[idx])),
+        }
+    }
+
+    fn slice(&self, r: &Range<usize>) -> Cow<'_, [u8]> {
+        self.raw
+            .peek_from(r.start, r.len())
+            .unwrap_or(Cow::Borrowed(&[]))
+    }
+}
+
+impl<'src, F: Flavor> Clone for Head<'src, F> {
+    fn clone(&self) -> Head<'src, F> {
+        Head {
+            raw: self.raw.clone(),
+            start: self.start.clone(),
+            status: self.status,
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+/// Codec for heads of HTTP/1.x messages. Decoder finds the end of head
+/// and returns it as `Head` sharing data with received chain; body is
+/// left in chain. Empty lines before head are skipped, as RFC 9112
+/// recommends. Encoder appends serialized head.
+#[derive(Debug, Clone)]
+pub struct HeadCodec {
+    max_head_length: usize,
+}
+
+impl HeadCodec {
+    /// Creates codec with maximum head length of 64 KiB.
+    pub fn new() -> HeadCodec {
+        HeadCodec {
+            max_head_length: MAX_HEAD_LENGTH,
+        }
+    }
+
+    /// Sets maximum length of head, including the empty line ending it.
+    /// Decoding fails with `InvalidData` as soon as more data is received
+    /// without end of head.
+    pub fn max_head_length(mut self, max: usize) -> HeadCodec {
+        self.max_head_length = max;
+        self
+    }
+}
+
+impl Default for HeadCodec {
+    fn default() -> HeadCodec {
+        HeadCodec::new()
+    }
+}
+
+impl<'src, F: Flavor> Decoder<'src, F> for HeadCodec {
+    type Item = Head<'src, F>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut Chain<'src, F>) -> io::Result<Option<Head<'src, F>>> {
+        while src.peek(2).as_deref() == Some(b"\r\n") {
+            src.drain(2);
+        }
+        let end = src.find(b"\r\n\r\n");
+        // Head, including the empty line, takes up to max_head_length bytes;
+        // incomplete head needs at least one more byte
+        let min_length = end.map_or(src.len() + 1, |offs| offs + 4);
+        if min_length > self.max_head_length {
+            return Err(invalid("head is longer than max_head_length"));
+        }
+        match end {
+            Some(offs) => {
+                let raw = src.split_to(offs + 2);
+                src.drain(2);
+                Head::parse(raw).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'src, F: Flavor> Encoder<'src, Head<'src, F>, F> for HeadCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Head<'src, F>, dst: &mut Chain<'src, F>) -> io::Result<()> {
+        dst.concat(item.into_chain());
+        Ok(())
+    }
+}
+
+/// Parses start line *line*, that starts at *offs* in head. Returns
+/// offsets of its tokens and status for response.
+fn parse_start_line(line: &[u8], offs: usize) -> io::Result<([Range<usize>; 3], Option<u16>)> {
+    let mut parts = line.splitn(3, |&b| b == b' ');
+    let first = parts.next().unwrap_or(&[]);
+    let second = parts.next().ok_or_else(|| invalid("invalid start line"))?;
+    let third = parts.next();
+    let r1 = offs..offs + first.len();
+    let r2 = r1.end + 1..r1.end + 1 + second.len();
+    let r3 = match third {
+        Some(t) => r2.end + 1..r2.end + 1 + t.len(),
+        None => r2.end..r2.end,
+    };
+    if first.starts_with(b"HTTP/") {
+        // Response: reason phrase may be missing, status is 100..1000 as in
+        // `Head::response`
+        let status = match second {
+            [a, b, c] if *a != b'0' && second.iter().all(u8::is_ascii_digit) => {
+                u16::from(a - b'0') * 100 + u16::from(b - b'0') * 10 + u16::from(c - b'0')
+            }
+            _ => return Err(invalid("invalid status")),
+        };
+        if !is_version(first) || !is_text(third.unwrap_or(&[])) {
+            return Err(invalid("invalid status line"));
+        }
+        Ok(([r1, r2, r3], Some(status)))
+    } else {
+        match third {
+            Some(version) if is_token(first) && is_target(second) && is_version(version) => {
+                Ok(([r1, r2, r3], None))
+            }
+            _ => Err(invalid("invalid request line")),
+        }
+    }
+}
+
+/// Parses header line *line*, that takes *pos* in head.
+fn parse_header(line: &[u8], pos: Range<usize>) -> io::Result<HeaderPos> {
+    let colon = match line.iter().position(|&b| b == b':') {
+        Some(colon) if is_token(&line[..colon]) => colon,
+        _ => return Err(invalid("invalid header line")),
+    };
+    let value = &line[colon + 1..];
+    let from = value
+        .iter()
+        .position(|&b| b != b' ' && b != b'\t')
+        .unwrap_or(value.len());
+    let to = value
+        .iter()
+        .rposition(|&b| b != b' ' && b != b'\t')
+        .map_or(from, |p| p + 1);
+    if !is_text(&value[from..to]) {
+        return Err(invalid("invalid header value"));
+    }
+    let value_start = pos.start + colon + 1;
+    Ok(HeaderPos {
+        name: pos.start..pos.start + colon,
+        value: value_start + from..value_start + to,
+        line: pos.start..pos.end + 2,
+    })
+}
+
+fn is_token(s: &[u8]) -> bool {
+    !s.is_empty()
+        && s.iter()
+            .all(|&b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+fn is_target(s: &[u8]) -> bool {
+    !s.is_empty() && s.iter().all(|&b| b.is_ascii_graphic() || b >= 0x80)
+}
+
+fn is_version(s: &[u8]) -> bool {
+    s == b"HTTP/1.0" || s == b"HTTP/1.1"
+}
+
+// Field value or reason phrase: no control characters except tab
+fn is_text(s: &[u8]) -> bool {
+    s.iter().all(|&b| b == b'\t' || (b >= 0x20 && b != 0x7f))
+}
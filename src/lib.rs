@@ -7,7 +7,17 @@
 //! ... docs are to be written
 //!
 
-pub use crate::chainbuf::Chain;
+pub use crate::chainbuf::{
+    Atomic, Chain, ChainCursor, DataHolder, Flavor, Lines, Local, MatchIndices, PatternSet,
+    Searcher, Split, SyncChain,
+};
+
+#[cfg(feature = "regex")]
+pub use crate::chainbuf::Regex;
+
+pub mod codec;
+pub mod http;
+pub mod resp;
 
 // XXX: for tests only, to remove, probably.
 pub use crate::chainbuf::CHB_MIN_SIZE;
@@ -0,0 +1,493 @@
+//! Redis serialization protocol (RESP2 and RESP3) on top of `Chain`.
+//!
+//! `RespCodec` decodes replies as `Value`s and encodes `Command`s.
+//! # Example
+//! ```
+//! use chainbuf::codec::{Decoder, Encoder};
+//! use chainbuf::resp::{Command, RespCodec, Value};
+//! use chainbuf::Chain;
+//! let mut codec = RespCodec::new();
+//! let mut out = Chain::new();
+//! let value = vec![b'x'; 100_000];
+//! codec.encode(Command::new("SET").arg("key").arg_vec(value), &mut out).unwrap();
+//! assert_eq!(out.peek(17).unwrap(), "*3\r\n$3\r\nSET\r\n$3\r\n".as_bytes());
+//!
+//! let mut replies = Chain::new();
+//! replies.append_bytes("*2\r\n$5\r\nhel".as_bytes());
+//! assert!(codec.decode(&mut replies).unwrap().is_none()); // incomplete
+//! replies.append_bytes("lo\r\n:42\r\n".as_bytes());
+//! match codec.decode(&mut replies).unwrap() {
+//!     Some(Value::Array(items)) => match &items[..] {
+//!         [Value::BulkString(s), Value::Integer(42)] => assert_eq!(s.len(), 5),
+//!         _ => panic!("unexpected items"),
+//!     },
+//!     _ => panic!("expected array"),
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::cmp;
+use std::io;
+use std::io::Write;
+use std::ops::Range;
+use std::str;
+
+use crate::chainbuf::{Chain, Flavor, Local};
+use crate::codec::{invalid, Decoder, Encoder};
+
+// Limit of line with simple value or length of aggregate
+const MAX_LINE: usize = 64 * 1024;
+// Limit of nesting of aggregate values
+const MAX_DEPTH: usize = 64;
+// Default limit of bulk string length, same as in Redis
+const MAX_BULK_LENGTH: usize = 512 * 1024 * 1024;
+
+/// Value of RESP reply. Strings are chains sharing data with received
+/// one, no data copy happens. All kinds of null are decoded as `Null`.
+pub enum Value<'src, F: Flavor = Local> {
+    /// Simple string, `+`.
+    SimpleString(Chain<'src, F>),
+    /// Simple error, `-`.
+    Error(Chain<'src, F>),
+    /// Integer, `:`.
+    Integer(i64),
+    /// Bulk string, `$`.
+    BulkString(Chain<'src, F>),
+    /// Array, `*`.
+    Array(Vec<Value<'src, F>>),
+    /// Null bulk string or array of RESP2, or RESP3 null, `_`.
+    Null,
+    /// Boolean, `#`.
+    Boolean(bool),
+    /// Double, `,`.
+    Double(f64),
+    /// Big number, `(`, as its decimal representation.
+    BigNumber(Chain<'src, F>),
+    /// Bulk error, `!`.
+    BulkError(Chain<'src, F>),
+    /// Verbatim string, `=`, including encoding prefix, like `txt:`.
+    VerbatimString(Chain<'src, F>),
+    /// Map, `%`.
+    Map(Vec<(Value<'src, F>, Value<'src, F>)>),
+    /// Set, `~`.
+    Set(Vec<Value<'src, F>>),
+    /// Attributes, `|`, with the value they are attached to.
+    Attribute(Vec<(Value<'src, F>, Value<'src, F>)>, Box<Value<'src, F>>),
+    /// Push data, `>`.
+    Push(Vec<Value<'src, F>>),
+}
+
+/// Redis command: array of bulk strings. Arguments are added with
+/// builder methods; `arg` copies data, while `arg_slice`, `arg_vec` and
+/// `arg_chain` attach it to command as is, so large values are never
+/// copied.
+/// # Example
+/// ```
+/// use chainbuf::codec::Encoder;
+/// use chainbuf::resp::{Command, RespCodec};
+/// use chainbuf::Chain;
+/// let value = "large value".to_string().into_bytes();
+/// let mut dst = Chain::new();
+/// let cmd = Command::new("SET").arg("k").arg_slice(&value[..5]);
+/// RespCodec::new().encode(cmd, &mut dst).unwrap();
+/// assert_eq!(
+///     dst.pullup_all().unwrap(),
+///     "*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$5\r\nlarge\r\n".as_bytes()
+/// );
+/// ```
+pub struct Command<'src, F: Flavor = Local> {
+    args: usize,
+    data: Chain<'src, F>,
+}
+
+impl<'src, F: Flavor> Command<'src, F> {
+    /// Creates command with name *name* and no arguments.
+    pub fn new(name: &str) -> Command<'src, F> {
+        let cmd = Command {
+            args: 0,
+            data: Chain::default(),
+        };
+        cmd.arg(name)
+    }
+
+    /// Adds copy of *arg*.
+    pub fn arg<A: AsRef<[u8]>>(mut self, arg: A) -> Command<'src, F> {
+        let arg = arg.as_ref();
+        self.bulk_header(arg.len());
+        self.data.append_bytes(arg);
+        self.data.append_bytes(b"\r\n");
+        self
+    }
+
+    /// Adds *arg*, borrowing it (see `Chain::append_slice`).
+    pub fn arg_slice(mut self, arg: &'src [u8]) -> Command<'src, F> {
+        self.bulk_header(arg.len());
+        self.data.append_slice(arg);
+        self.data.append_bytes(b"\r\n");
+        self
+    }
+
+    /// Adds *arg*, taking ownership of it (see `Chain::append_vec`).
+    pub fn arg_vec(mut self, arg: Vec<u8>) -> Command<'src, F> {
+        self.bulk_header(arg.len());
+        self.data.append_vec(arg);
+        self.data.append_bytes(b"\r\n");
+        self
+    }
+
+    /// Adds all data of *arg*.
+    pub fn arg_chain(mut self, arg: Chain<'src, F>) -> Command<'src, F> {
+        self.bulk_header(arg.len());
+        self.data.concat(arg);
+        self.data.append_bytes(b"\r\n");
+        self
+    }
+
+    fn bulk_header(&mut self, len: usize) {
+        self.args += 1;
+        // Writing to chain never fails
+        write!(self.data, "${}\r\n", len).unwrap();
+    }
+}
+
+/// Codec for RESP: decodes replies, encodes commands.
+///
+/// Decoder returns `Ok(None)` until the whole reply is received and
+/// leaves chain intact then; complete reply is removed from chain.
+/// Malformed reply, as well as RESP3 streamed strings and aggregates,
+/// which are not supported, fail with `InvalidData`.
+///
+/// Codec remembers how far incomplete reply was checked, so each line is
+/// looked at once and values are built only when the whole reply is
+/// received. Hence codec instance should be used with one stream only.
+#[derive(Debug, Clone)]
+pub struct RespCodec {
+    max_bulk_length: usize,
+    scan: Scan,
+}
+
+// Progress of check that reply at the beginning of chain is complete
+#[derive(Debug, Clone, Default)]
+struct Scan {
+    // Offset of the next line to check
+    pos: usize,
+    // Offset to continue search for the end of that line from
+    searched: usize,
+    // Numbers of values still expected by open aggregates
+    pending: Vec<u64>,
+}
+
+impl RespCodec {
+    /// Creates codec with maximum bulk string length of 512 MiB.
+    pub fn new() -> RespCodec {
+        RespCodec {
+            max_bulk_length: MAX_BULK_LENGTH,
+            scan: Scan::default(),
+        }
+    }
+
+    /// Sets maximum length of bulk string, decoding of longer one fails
+    /// with `InvalidData` as soon as its length is received.
+    pub fn max_bulk_length(mut self, max: usize) -> RespCodec {
+        self.max_bulk_length = max;
+        self
+    }
+}
+
+impl Default for RespCodec {
+    fn default() -> RespCodec {
+        RespCodec::new()
+    }
+}
+
+impl<'src, F: Flavor> Decoder<'src, F> for RespCodec {
+    type Item = Value<'src, F>;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut Chain<'src, F>) -> io::Result<Option<Value<'src, F>>> {
+        let parser = Parser {
+            src,
+            max_bulk_length: self.max_bulk_length,
+        };
+        if parser.scan(&mut self.scan)?.is_none() {
+            return Ok(None);
+        }
+        self.scan = Scan::default();
+        match parser.value(0, 0)? {
+            Some((value, end)) => {
+                src.drain(end);
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<'src, F: Flavor> Encoder<'src, Command<'src, F>, F> for RespCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Command<'src, F>, dst: &mut Chain<'src, F>) -> io::Result<()> {
+        write!(dst, "*{}\r\n", item.args)?;
+        dst.concat(item.data);
+        Ok(())
+    }
+}
+
+// Walks chain without changing it. Parsing functions return value and
+// offset after it, or None if chain ends before value.
+struct Parser<'a, 'src, F: Flavor> {
+    src: &'a Chain<'src, F>,
+    max_bulk_length: usize,
+}
+
+type Parsed<'src, F> = io::Result<Option<(Value<'src, F>, usize)>>;
+
+impl<'a, 'src, F: Flavor> Parser<'a, 'src, F> {
+    fn value(&self, pos: usize, depth: usize) -> Parsed<'src, F> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("reply is nested too deep"));
+        }
+        let (line, next) = match self.line(pos)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        let content = line.start + 1..line.end;
+        let value = match self.kind(&line) {
+            Some(b'+') => Value::SimpleString(self.src.slice(content)),
+            Some(b'-') => Value::Error(self.src.slice(content)),
+            Some(b':') => Value::Integer(self.int(&content)?),
+            Some(b'_') if content.is_empty() => Value::Null,
+            Some(b'#') => match &self.bytes(&content)[..] {
+                b"t" => Value::Boolean(true),
+                b"f" => Value::Boolean(false),
+                _ => return Err(invalid("invalid boolean")),
+            },
+            Some(b',') => Value::Double(self.parse(&content)?),
+            Some(b'(') => Value::BigNumber(self.src.slice(content)),
+            Some(t @ b'$') | Some(t @ b'!') | Some(t @ b'=') => {
+                return self.bulk(t, &content, next);
+            }
+            Some(t @ b'*') | Some(t @ b'~') | Some(t @ b'>') => {
+                return self.list(t, &content, next, depth);
+            }
+            Some(t @ b'%') | Some(t @ b'|') => {
+                return self.map(t, &content, next, depth);
+            }
+            _ => return Err(invalid("unknown reply type")),
+        };
+        Ok(Some((value, next)))
+    }
+
+    fn bulk(&self, t: u8, content: &Range<usize>, next: usize) -> Parsed<'src, F> {
+        let len = self.int(content)?;
+        if t == b'$' && len == -1 {
+            return Ok(Some((Value::Null, next)));
+        }
+        let end = match self.bulk_end(len, next)? {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+        let data = self.src.slice(next..end);
+        let value = match t {
+            b'$' => Value::BulkString(data),
+            b'!' => Value::BulkError(data),
+            _ => Value::VerbatimString(data),
+        };
+        Ok(Some((value, end + 2)))
+    }
+
+    fn list(&self, t: u8, content: &Range<usize>, next: usize, depth: usize) -> Parsed<'src, F> {
+        let n = self.int(content)?;
+        if t == b'*' && n == -1 {
+            return Ok(Some((Value::Null, next)));
+        }
+        let (items, next) = match self.values(n, next, depth)? {
+            Some(parsed) => parsed,
+            None => return Ok(None),
+        };
+        let value = match t {
+            b'*' => Value::Array(items),
+            b'~' => Value::Set(items),
+            _ => Value::Push(items),
+        };
+        Ok(Some((value, next)))
+    }
+
+    fn map(&self, t: u8, content: &Range<usize>, next: usize, depth: usize) -> Parsed<'src, F> {
+        let n = self.int(content)?;
+        let n = n
+            .checked_mul(2)
+            .ok_or_else(|| invalid("invalid map length"))?;
+        let (items, mut next) = match self.values(n, next, depth)? {
+            Some(parsed) => parsed,
+            None => return Ok(None),
+        };
+        let mut pairs = Vec::with_capacity(items.len() / 2);
+        let mut items = items.into_iter();
+        while let (Some(k), Some(v)) = (items.next(), items.next()) {
+            pairs.push((k, v));
+        }
+        let value = if t == b'%' {
+            Value::Map(pairs)
+        } else {
+            let (value, after) = match self.value(next, depth + 1)? {
+                Some(parsed) => parsed,
+                None => return Ok(None),
+            };
+            next = after;
+            Value::Attribute(pairs, Box::new(value))
+        };
+        Ok(Some((value, next)))
+    }
+
+    /// Checks bulk string of *len* bytes, starting from *pos*, and returns
+    /// offset of its end, without CRLF.
+    fn bulk_end(&self, len: i64, pos: usize) -> io::Result<Option<usize>> {
+        if len < 0 || len as u64 > self.max_bulk_length as u64 {
+            return Err(invalid("invalid bulk string length"));
+        }
+        let end = pos + len as usize;
+        if self.src.len() < end + 2 {
+            return Ok(None);
+        }
+        if &self.bytes(&(end..end + 2))[..] != b"\r\n" {
+            return Err(invalid("bulk string is not followed by CRLF"));
+        }
+        Ok(Some(end))
+    }
+
+    /// Parses *n* values, starting from *pos*.
+    fn values(
+        &self,
+        n: i64,
+        mut pos: usize,
+        depth: usize,
+    ) -> io::Result<Option<(Vec<Value<'src, F>>, usize)>> {
+        if n < 0 {
+            return Err(invalid("invalid aggregate length"));
+        }
+        // Length comes from peer, so don't trust it much
+        let mut items = Vec::with_capacity(cmp::min(n as usize, 1024));
+        for _ in 0..n {
+            match self.value(pos, depth + 1)? {
+                Some((item, next)) => {
+                    items.push(item);
+                    pos = next;
+                }
+                None => return Ok(None),
+            }
+        }
+        Ok(Some((items, pos)))
+    }
+
+    /// Checks that reply starting at the beginning of chain is complete,
+    /// continuing from where previous check of *scan* stopped. Only
+    /// lengths and types are checked, values are not built. Returns offset
+    /// after the reply or None if it is incomplete.
+    fn scan(&self, scan: &mut Scan) -> io::Result<Option<usize>> {
+        loop {
+            if scan.pending.len() > MAX_DEPTH {
+                return Err(invalid("reply is nested too deep"));
+            }
+            let (line, mut end) = match self.line_from(scan.pos, scan.searched)? {
+                Some(found) => found,
+                None => {
+                    // CR may be the last byte
+                    scan.searched = cmp::max(scan.pos, self.src.len().saturating_sub(1));
+                    return Ok(None);
+                }
+            };
+            let content = line.start + 1..line.end;
+            let mut n = 0;
+            match self.kind(&line) {
+                Some(b'+') | Some(b'-') | Some(b':') | Some(b'_') | Some(b'#') | Some(b',')
+                | Some(b'(') => {}
+                Some(t @ b'$') | Some(t @ b'!') | Some(t @ b'=') => {
+                    let len = self.int(&content)?;
+                    if t != b'$' || len != -1 {
+                        match self.bulk_end(len, end)? {
+                            Some(at) => end = at + 2,
+                            None => return Ok(None),
+                        }
+                    }
+                }
+                Some(t @ b'*') | Some(t @ b'~') | Some(t @ b'>') => {
+                    n = self.int(&content)?;
+                    if t == b'*' && n == -1 {
+                        n = 0;
+                    } else if n < 0 {
+                        return Err(invalid("invalid aggregate length"));
+                    }
+                }
+                Some(t @ b'%') | Some(t @ b'|') => {
+                    n = self
+                        .int(&content)?
+                        .checked_mul(2)
+                        .ok_or_else(|| invalid("invalid map length"))?;
+                    if n < 0 {
+                        return Err(invalid("invalid aggregate length"));
+                    }
+                    if t == b'|' {
+                        // Attributes are followed by value they are attached to
+                        n += 1;
+                    }
+                }
+                _ => return Err(invalid("unknown reply type")),
+            }
+            scan.pos = end;
+            scan.searched = end;
+            if n > 0 {
+                scan.pending.push(n as u64);
+                continue;
+            }
+            // Value is complete, and so may be aggregates it ends
+            loop {
+                match scan.pending.last_mut() {
+                    None => return Ok(Some(end)),
+                    Some(left) if *left > 1 => {
+                        *left -= 1;
+                        break;
+                    }
+                    Some(_) => {
+                        scan.pending.pop();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Finds line starting at *pos* and returns its range without CRLF and
+    /// offset of the next line.
+    fn line(&self, pos: usize) -> io::Result<Option<(Range<usize>, usize)>> {
+        self.line_from(pos, pos)
+    }
+
+    /// Same as `line`, but CRLF is searched from *from* on.
+    fn line_from(&self, pos: usize, from: usize) -> io::Result<Option<(Range<usize>, usize)>> {
+        match self.src.find_from(from, b"\r\n") {
+            Some(end) if end - pos <= MAX_LINE => Ok(Some((pos..end, end + 2))),
+            None if self.src.len() - pos <= MAX_LINE => Ok(None),
+            _ => Err(invalid("reply line is too long")),
+        }
+    }
+
+    /// Returns type byte of *line*.
+    fn kind(&self, line: &Range<usize>) -> Option<u8> {
+        self.src.peek_from(line.start, 1).map(|b| b[0])
+    }
+
+    fn bytes(&self, r: &Range<usize>) -> Cow<'_, [u8]> {
+        self.src.peek_from(r.start, r.len()).unwrap_or_default()
+    }
+
+    fn int(&self, r: &Range<usize>) -> io::Result<i64> {
+        self.parse(r)
+    }
+
+    fn parse<T: str::FromStr>(&self, r: &Range<usize>) -> io::Result<T> {
+        str::from_utf8(&self.bytes(r))
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| invalid("invalid number"))
+    }
+}
@@ -8,6 +8,7 @@ mod integration_test {
         use std::iter::repeat;
 
         #[test]
+        #[allow(clippy::clone_on_copy, clippy::manual_repeat_n, clippy::map_clone)]
         fn test_write_to_fd_works() {
             // Run this test with some pipes so we don't have to mess around with
             // opening or closing files.
@@ -47,6 +48,69 @@ mod integration_test {
         }
     }
 
+    #[cfg(feature = "nix")]
+    mod test_readv {
+        use chainbuf::Chain;
+        use nix::unistd::{close, pipe, read, write};
+        use rand::{thread_rng, Rng};
+
+        #[test]
+        fn test_read_from_fd_fills_tail_and_new_node() {
+            let s: String = thread_rng().gen_ascii_chars().take(100).collect();
+            let (reader, writer) = pipe().unwrap();
+            assert_eq!(write(writer, s.as_bytes()).unwrap(), 100);
+            let _ = close(writer);
+
+            let mut chain = Chain::new();
+            chain.append_bytes("ab".as_bytes());
+            assert_eq!(chain.read_from_fd(reader, 64).unwrap(), 64);
+            assert_eq!(chain.len(), 66);
+            assert_eq!(chain.read_from_fd(reader, 1024).unwrap(), 36);
+            assert_eq!(chain.len(), 102);
+            // end of file adds nothing
+            assert_eq!(chain.read_from_fd(reader, 1024).unwrap(), 0);
+            assert_eq!(chain.len(), 102);
+            let mut expected = b"ab".to_vec();
+            expected.extend_from_slice(s.as_bytes());
+            assert_eq!(chain.pullup_all().unwrap(), &expected[..]);
+            let _ = close(reader);
+        }
+
+        #[test]
+        fn test_read_from_fd_does_not_touch_shared_tail() {
+            let (reader, writer) = pipe().unwrap();
+            assert_eq!(write(writer, "world".as_bytes()).unwrap(), 5);
+            let _ = close(writer);
+
+            let mut chain = Chain::new();
+            chain.append_bytes("hello".as_bytes());
+            let mut copy = chain.clone();
+            assert_eq!(chain.read_from_fd(reader, 5).unwrap(), 5);
+            copy.append_bytes("there".as_bytes());
+            assert_eq!(chain.pullup_all().unwrap(), "helloworld".as_bytes());
+            assert_eq!(copy.pullup_all().unwrap(), "hellothere".as_bytes());
+            let _ = close(reader);
+        }
+
+        #[test]
+        fn test_read_from_fd_then_write_to_fd() {
+            let (in_reader, in_writer) = pipe().unwrap();
+            let (out_reader, out_writer) = pipe().unwrap();
+            assert_eq!(write(in_writer, "proxied".as_bytes()).unwrap(), 7);
+
+            let mut chain = Chain::new();
+            assert_eq!(chain.read_from_fd(in_reader, 4096).unwrap(), 7);
+            assert_eq!(chain.write_to_fd(out_writer, None, None).unwrap(), 7);
+            assert_eq!(chain.len(), 0);
+            let mut buf = [0u8; 16];
+            assert_eq!(read(out_reader, &mut buf).unwrap(), 7);
+            assert_eq!(&buf[..7], "proxied".as_bytes());
+            for fd in &[in_reader, in_writer, out_reader, out_writer] {
+                let _ = close(*fd);
+            }
+        }
+    }
+
     #[cfg(feature = "nix")]
     #[allow(deprecated)]
     mod test_append_file {
@@ -92,4 +156,153 @@ mod integration_test {
             assert_eq!(data, &v[..]);
         }
     }
+
+    #[cfg(feature = "nix")]
+    #[allow(deprecated)]
+    mod test_write_file_nodes {
+        use chainbuf::http::Head;
+        use chainbuf::Chain;
+        use nix::fcntl as nf;
+        use nix::unistd::{close, pipe, read};
+        use rand::{thread_rng, Rng};
+        use std::fs;
+        use std::path::PathBuf;
+        use tempdir::TempDir;
+
+        fn random_file(tmpd: &TempDir, size: usize) -> (PathBuf, Vec<u8>) {
+            let s: String = thread_rng().gen_ascii_chars().take(size).collect();
+            let mut p = tmpd.path().to_path_buf();
+            p.push("served_file");
+            fs::write(&p, s.as_bytes()).unwrap();
+            (p, s.into_bytes())
+        }
+
+        fn read_all(fd: i32, size: usize) -> Vec<u8> {
+            let mut buf = vec![0u8; size];
+            let mut got = 0;
+            while got < size {
+                let n = read(fd, &mut buf[got..]).unwrap();
+                assert!(n > 0);
+                got += n;
+            }
+            buf
+        }
+
+        #[test]
+        fn test_write_to_fd_mixes_memory_and_file_nodes() {
+            let tmpd = TempDir::new("chain-test").unwrap();
+            let (p, data) = random_file(&tmpd, 1024);
+            let mut chain = Chain::new();
+            chain.append_bytes("head:".as_bytes());
+            chain.append_file(&p).unwrap();
+            chain.append_slice(":tail".as_bytes());
+            // start in the middle of the file node
+            chain.drain(10);
+            let mut expected = data[5..].to_vec();
+            expected.extend_from_slice(":tail".as_bytes());
+
+            let (reader, writer) = pipe().unwrap();
+            let written = chain.write_to_fd(writer, None, None).unwrap();
+            assert_eq!(written, expected.len());
+            assert_eq!(chain.len(), 0);
+            assert_eq!(read_all(reader, written), expected);
+            let _ = close(writer);
+            let _ = close(reader);
+        }
+
+        #[test]
+        fn test_serve_file_with_http_head() {
+            let tmpd = TempDir::new("chain-test").unwrap();
+            let (p, data) = random_file(&tmpd, 4096);
+            let mut response = Chain::new();
+            response.append_file(&p).unwrap();
+            let mut head = Head::response(200, "OK");
+            let len = response.len().to_string();
+            head.append_header(b"Content-Length", len.as_bytes())
+                .unwrap();
+            head.prepend_to(&mut response);
+            let mut expected = b"HTTP/1.1 200 OK\r\nContent-Length: 4096\r\n\r\n".to_vec();
+            expected.extend_from_slice(&data);
+
+            let (reader, writer) = pipe().unwrap();
+            let written = response.write_to_fd(writer, None, None).unwrap();
+            assert_eq!(written, expected.len());
+            assert_eq!(read_all(reader, written), expected);
+            let _ = close(writer);
+            let _ = close(reader);
+        }
+
+        #[test]
+        fn test_write_to_fd_drains_partial_file_write() {
+            let tmpd = TempDir::new("chain-test").unwrap();
+            // larger than default pipe buffer
+            let (p, data) = random_file(&tmpd, 256 * 1024);
+            let mut chain = Chain::new();
+            chain.append_file(&p).unwrap();
+            chain.append_bytes("!".as_bytes());
+            let total = chain.len();
+
+            let (reader, writer) = pipe().unwrap();
+            nf::fcntl(writer, nf::FcntlArg::F_SETFL(nf::OFlag::O_NONBLOCK)).unwrap();
+            let mut out = Vec::with_capacity(total);
+            while !chain.is_empty() {
+                let written = chain.write_to_fd(writer, None, None).unwrap();
+                assert!(written > 0);
+                assert_eq!(chain.len() + out.len() + written, total);
+                out.extend_from_slice(&read_all(reader, written));
+            }
+            assert_eq!(&out[..data.len()], &data[..]);
+            assert_eq!(&out[data.len()..], "!".as_bytes());
+            let _ = close(writer);
+            let _ = close(reader);
+        }
+    }
+
+    #[cfg(feature = "nix")]
+    mod test_framed_fd {
+        use chainbuf::codec::{DelimiterCodec, Fd, Framed, LengthDelimitedCodec};
+        use chainbuf::Chain;
+        use nix::unistd::{close, pipe, write};
+
+        #[test]
+        fn test_framed_over_pipe() {
+            let (reader, writer) = pipe().unwrap();
+            let mut tx = Framed::new(Fd(writer), LengthDelimitedCodec::new());
+            let mut big = Chain::new();
+            big.append_bytes(&[7u8; 20000]);
+            tx.write_frame("first".as_bytes()).unwrap();
+            tx.write_frame(big).unwrap();
+            tx.send("".as_bytes()).unwrap();
+            let _ = close(writer);
+
+            let rx = Framed::new(Fd(reader), LengthDelimitedCodec::new());
+            let frames: Vec<Vec<u8>> = rx
+                .map(|f| {
+                    let f = f.unwrap();
+                    f.copy_bytes_from(0, f.len())
+                })
+                .collect();
+            assert_eq!(frames, vec![b"first".to_vec(), vec![7u8; 20000], vec![]]);
+            let _ = close(reader);
+        }
+
+        #[test]
+        fn test_framed_incomplete_frame_at_eof() {
+            let (reader, writer) = pipe().unwrap();
+            let mut tx = Framed::new(Fd(writer), DelimiterCodec::new(b"\n"));
+            tx.send("a".as_bytes()).unwrap();
+            assert_eq!(write(writer, &[0, 0, 0, 5, b'b']).unwrap(), 5);
+            let _ = close(writer);
+
+            let mut rx = Framed::new(Fd(reader), DelimiterCodec::new(b"\n"));
+            assert_eq!(rx.read_frame().unwrap().unwrap().len(), 1);
+            let rest = rx.into_parts().2;
+            let mut rx = Framed::with_buffer(Fd(reader), LengthDelimitedCodec::new(), rest);
+            assert_eq!(
+                rx.read_frame().err().unwrap().kind(),
+                std::io::ErrorKind::UnexpectedEof
+            );
+            let _ = close(reader);
+        }
+    }
 }
@@ -1,8 +1,16 @@
 #[cfg(test)]
 mod unit_test {
-    use chainbuf::{Chain, CHB_MIN_SIZE};
+    use chainbuf::codec::{Decoder, DelimiterCodec, Encoder, Framed, LengthDelimitedCodec};
+    use chainbuf::http::{Chunk, ChunkedDecoder, ChunkedEncoder, Head, HeadCodec};
+    use chainbuf::resp::{Command, RespCodec, Value};
+    use chainbuf::{Chain, DataHolder, PatternSet, Searcher, SyncChain, CHB_MIN_SIZE};
     use rand::{thread_rng, Rng};
+    use std::io;
+    use std::io::{BufRead, IoSlice, Read, Seek, SeekFrom, Write};
     use std::iter::repeat;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
 
     #[test]
     fn test_append_bytes_changes_length() {
@@ -53,7 +61,7 @@ mod unit_test {
 
     #[test]
     fn test_pullup_returns_none_on_empty_chain() {
-        let chain = Chain::new();
+        let mut chain = Chain::new();
         assert!(chain.pullup(1).is_none());
     }
 
@@ -87,6 +95,7 @@ mod unit_test {
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy, clippy::map_clone)]
     fn test_pullup_works_on_large_sequences() {
         let mut chain = Chain::new();
         let total = 2048usize;
@@ -154,6 +163,7 @@ mod unit_test {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_mut_passed)]
     fn test_append_copies_data() {
         let mut chain1 = Chain::new();
         let mut chain2 = Chain::new();
@@ -236,6 +246,7 @@ mod unit_test {
     }
 
     #[test]
+    #[allow(clippy::manual_repeat_n)]
     fn test_reserve_returns_free_buffer() {
         let mut chain = Chain::new();
         chain.append_bytes("helloworld".as_bytes());
@@ -245,6 +256,7 @@ mod unit_test {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn test_reserve_and_written_modifies_chain() {
         let mut chain = Chain::new();
         let s = "helloworld".as_bytes();
@@ -391,6 +403,7 @@ mod unit_test {
     }
 
     #[test]
+    #[allow(clippy::needless_as_bytes, clippy::useless_vec)]
     fn test_copy_bytes_from_returns_copies_bytes() {
         let mut chain = Chain::new();
         let mut offs = 0;
@@ -410,6 +423,16 @@ mod unit_test {
         assert_eq!(&res[..], v[2].as_bytes());
     }
 
+    #[test]
+    fn test_copy_bytes_from_stops_at_requested_size_in_next_node() {
+        let mut chain = Chain::new();
+        chain.append_slice("hello".as_bytes());
+        chain.append_slice("world".as_bytes());
+        chain.append_slice("example".as_bytes());
+        assert_eq!(chain.copy_bytes_from(3, 5), "lowor".as_bytes());
+        assert_eq!(chain.copy_bytes_from(4, 8), "oworldex".as_bytes());
+    }
+
     #[test]
     fn test_copy_bytes_returns_less_than_requested_if_chain_does_not_have_data() {
         let mut chain = Chain::new();
@@ -422,12 +445,13 @@ mod unit_test {
 
     #[test]
     fn test_pullup_from_returns_none_on_empty_chain() {
-        let chain = Chain::new();
+        let mut chain = Chain::new();
         let res = chain.pullup_from(10, 10);
         assert!(res.is_none());
     }
 
     #[test]
+    #[allow(clippy::needless_as_bytes, clippy::useless_vec)]
     fn test_pullup_from_returns_data_from_correct_offset() {
         let mut chain = Chain::new();
         let mut offs = 0;
@@ -450,7 +474,7 @@ mod unit_test {
 
     #[test]
     fn test_pullup_to_returns_none_on_empty_chain() {
-        let chain = Chain::new();
+        let mut chain = Chain::new();
         let res = chain.pullup_to("helloworld".as_bytes());
         assert!(res.is_none());
     }
@@ -464,6 +488,7 @@ mod unit_test {
     }
 
     #[test]
+    #[allow(clippy::useless_vec)]
     fn test_pullup_to_returns_correct_sequence() {
         fn join_string(del: &str, parts: &[&str]) -> String {
             let mut s = String::new();
@@ -491,4 +516,1764 @@ mod unit_test {
         assert!(res.is_some());
         assert_eq!(res.unwrap(), patt.as_bytes());
     }
+
+    #[test]
+    fn test_read_copies_and_drains_data() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        chain.append_bytes("world".as_bytes());
+        let mut buf = [0u8; 7];
+        let res = chain.read(&mut buf);
+        assert_eq!(res.unwrap(), 7);
+        assert_eq!(&buf[..], "hellowo".as_bytes());
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.pullup_all().unwrap(), "rld".as_bytes());
+    }
+
+    #[test]
+    fn test_read_returns_zero_on_empty_chain() {
+        let mut chain = Chain::new();
+        let mut buf = [0u8; 7];
+        assert_eq!(chain.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_write_appends_data() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        assert_eq!(chain.write("world".as_bytes()).unwrap(), 5);
+        assert_eq!(chain.len(), 10);
+        assert_eq!(chain.pullup_all().unwrap(), "helloworld".as_bytes());
+    }
+
+    #[test]
+    fn test_write_vectored_appends_all_slices() {
+        let mut chain = Chain::new();
+        let bufs = [
+            IoSlice::new("hello".as_bytes()),
+            IoSlice::new("".as_bytes()),
+            IoSlice::new("world".as_bytes()),
+        ];
+        assert_eq!(chain.write_vectored(&bufs).unwrap(), 10);
+        assert_eq!(chain.fill_buf().unwrap(), "helloworld".as_bytes());
+    }
+
+    #[test]
+    fn test_fill_buf_returns_head_node_and_consume_drains() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        chain.append_slice("world".as_bytes());
+        assert_eq!(chain.fill_buf().unwrap(), "hello".as_bytes());
+        chain.consume(3);
+        assert_eq!(chain.fill_buf().unwrap(), "lo".as_bytes());
+        chain.consume(2);
+        assert_eq!(chain.fill_buf().unwrap(), "world".as_bytes());
+        chain.consume(5);
+        assert!(chain.fill_buf().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_chain_works_with_std_io_helpers() {
+        let mut src = Chain::new();
+        src.append_bytes("first\n".as_bytes());
+        src.append_bytes("sec".as_bytes());
+        src.append_bytes("ond\nthird".as_bytes());
+        let mut dst = Chain::new();
+        let copied = io::copy(&mut src, &mut dst).unwrap();
+        assert_eq!(copied, 18);
+        assert!(src.is_empty());
+        let lines: Vec<String> = BufRead::lines(dst).map(|l| l.unwrap()).collect();
+        assert_eq!(lines, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn test_cursor_reads_without_draining() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        chain.append_bytes("world".as_bytes());
+        let mut cursor = chain.cursor();
+        let mut buf = Vec::new();
+        assert_eq!(cursor.read_to_end(&mut buf).unwrap(), 10);
+        assert_eq!(&buf[..], "helloworld".as_bytes());
+        assert_eq!(cursor.position(), 10);
+        assert_eq!(cursor.remaining(), 0);
+        assert_eq!(chain.len(), 10);
+    }
+
+    #[test]
+    fn test_cursor_seek_moves_position() {
+        let mut chain = Chain::new();
+        for s in ["hello", "world", "example"].iter() {
+            chain.append_bytes(s.as_bytes());
+        }
+        let mut cursor = chain.cursor();
+        let mut buf = [0u8; 4];
+        assert_eq!(cursor.seek(SeekFrom::Start(8)).unwrap(), 8);
+        cursor.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], "ldex".as_bytes());
+        assert_eq!(cursor.seek(SeekFrom::Current(-10)).unwrap(), 2);
+        cursor.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], "llow".as_bytes());
+        assert_eq!(cursor.seek(SeekFrom::End(-3)).unwrap(), 14);
+        assert_eq!(cursor.fill_buf().unwrap(), "ple".as_bytes());
+    }
+
+    #[test]
+    fn test_cursor_seek_before_start_fails() {
+        let mut chain = Chain::new();
+        chain.append_bytes("helloworld".as_bytes());
+        let mut cursor = chain.cursor();
+        assert!(cursor.seek(SeekFrom::Current(-1)).is_err());
+        assert_eq!(cursor.position(), 0);
+    }
+
+    #[test]
+    fn test_cursor_seek_past_end_reads_nothing() {
+        let mut chain = Chain::new();
+        chain.append_bytes("helloworld".as_bytes());
+        let mut cursor = chain.cursor();
+        assert_eq!(cursor.seek(SeekFrom::End(5)).unwrap(), 15);
+        let mut buf = [0u8; 4];
+        assert_eq!(cursor.read(&mut buf).unwrap(), 0);
+        assert_eq!(cursor.seek(SeekFrom::Start(5)).unwrap(), 5);
+        assert_eq!(cursor.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf[..], "worl".as_bytes());
+    }
+
+    #[test]
+    fn test_cursor_fill_buf_walks_nodes() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        chain.append_slice("world".as_bytes());
+        let mut cursor = chain.cursor();
+        cursor.consume(2);
+        assert_eq!(cursor.fill_buf().unwrap(), "llo".as_bytes());
+        cursor.consume(3);
+        assert_eq!(cursor.fill_buf().unwrap(), "world".as_bytes());
+        let mut line = Vec::new();
+        cursor.seek(SeekFrom::Start(0)).unwrap();
+        cursor.read_until(b'w', &mut line).unwrap();
+        assert_eq!(&line[..], "hellow".as_bytes());
+    }
+
+    #[test]
+    fn test_append_bytes_writes_only_to_unshared_node() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        chain.append_bytes("world".as_bytes());
+        // Room left in uniquely owned node is reused
+        assert_eq!(chain.fill_buf().unwrap(), "helloworld".as_bytes());
+        let mut copy = chain.clone();
+        chain.append_bytes("!".as_bytes());
+        copy.append_bytes("?".as_bytes());
+        assert_eq!(chain.fill_buf().unwrap(), "helloworld".as_bytes());
+        assert_eq!(chain.pullup_all().unwrap(), "helloworld!".as_bytes());
+        assert_eq!(copy.pullup_all().unwrap(), "helloworld?".as_bytes());
+    }
+
+    #[test]
+    fn test_move_from_splits_node_in_second_half_of_chain() {
+        let mut chain1 = Chain::new();
+        let mut chain2 = Chain::new();
+        for s in ["hello", "world", "example"].iter() {
+            chain2.append_bytes(s.as_bytes());
+        }
+        let moved = chain1.move_from(&mut chain2, 12);
+        assert_eq!(moved, 12);
+        assert_eq!(chain1.pullup_all().unwrap(), "helloworldex".as_bytes());
+        assert_eq!(chain2.pullup_all().unwrap(), "ample".as_bytes());
+    }
+
+    #[test]
+    fn test_offset_lookups_survive_prepend_and_drain() {
+        let mut chain = Chain::new();
+        let mut model = Vec::new();
+        for i in 0..1000usize {
+            let s = format!("{}-", i);
+            chain.append_bytes(s.as_bytes());
+            model.extend_from_slice(s.as_bytes());
+        }
+        chain.drain(7);
+        model.drain(..7);
+        let p = "prefix".as_bytes();
+        chain.prepend_bytes(p);
+        model.splice(0..0, p.iter().cloned());
+        for &offs in [0usize, 3, 6, 100, 1000, model.len() - 5].iter() {
+            assert_eq!(chain.copy_bytes_from(offs, 5), &model[offs..offs + 5]);
+            assert_eq!(chain.pullup_from(offs, 4).unwrap(), &model[offs..offs + 4]);
+        }
+    }
+
+    #[test]
+    fn test_random_operations_match_vec_model() {
+        let mut rng = thread_rng();
+        let mut chain = Chain::new();
+        let mut model: Vec<u8> = Vec::new();
+        for _ in 0..2000usize {
+            let l = rng.gen_range(1, 20);
+            let s: String = rng.gen_ascii_chars().take(l).collect();
+            let b = s.as_bytes();
+            match rng.gen_range(0, 5) {
+                0 => {
+                    chain.append_bytes(b);
+                    model.extend_from_slice(b);
+                }
+                1 => {
+                    chain.prepend_bytes(b);
+                    model.splice(0..0, b.iter().cloned());
+                }
+                2 => {
+                    let n = rng.gen_range(0, model.len() + 2);
+                    chain.drain(n);
+                    model.drain(..std::cmp::min(n, model.len()));
+                }
+                3 if !model.is_empty() => {
+                    let n = rng.gen_range(0, model.len() + 1);
+                    let mut front = Chain::new();
+                    front.move_from(&mut chain, n);
+                    assert_eq!(front.copy_bytes_from(0, n), &model[..n]);
+                    front.move_all_from(&mut chain);
+                    chain = front;
+                }
+                _ if !model.is_empty() => {
+                    let offs = rng.gen_range(0, model.len());
+                    let size = rng.gen_range(1, model.len() - offs + 1);
+                    assert_eq!(chain.copy_bytes_from(offs, size), &model[offs..offs + size]);
+                }
+                _ => {}
+            }
+            assert_eq!(chain.len(), model.len());
+        }
+        assert_eq!(chain.copy_bytes_from(0, model.len()), model);
+    }
+
+    #[test]
+    fn test_peek_does_not_change_chain() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        chain.append_slice("world".as_bytes());
+        let chain = chain;
+        assert_eq!(&chain.peek(3).unwrap()[..], "hel".as_bytes());
+        assert_eq!(&chain.peek(10).unwrap()[..], "helloworld".as_bytes());
+        assert_eq!(&chain.peek_from(4, 2).unwrap()[..], "ow".as_bytes());
+        assert!(chain.peek(11).is_none());
+        assert!(chain.peek_from(8, 3).is_none());
+        assert!(chain.peek(0).is_none());
+        assert_eq!(chain.len(), 10);
+    }
+
+    #[test]
+    fn test_pullup_from_returns_none_when_too_much_data_requested() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        chain.append_slice("world".as_bytes());
+        assert!(chain.pullup_from(3, 8).is_none());
+        assert_eq!(chain.pullup_from(3, 7).unwrap(), "loworld".as_bytes());
+    }
+
+    #[test]
+    fn test_sync_chain_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>(_: &T) {}
+        let chain = SyncChain::default();
+        assert_send_sync(&chain);
+    }
+
+    #[test]
+    fn test_sync_chain_shares_data_across_threads() {
+        let mut chain = SyncChain::default();
+        chain.append_bytes("hello".as_bytes());
+        let shared = Arc::new(chain.clone());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let mut local = SyncChain::default();
+                    local.append(&shared);
+                    local.append_bytes("world".as_bytes());
+                    local.pullup_all().unwrap().to_vec()
+                })
+            })
+            .collect();
+        for h in handles {
+            assert_eq!(&h.join().unwrap()[..], "helloworld".as_bytes());
+        }
+        // Copy-on-write: appends in other threads did not touch shared data
+        assert_eq!(shared.len(), 5);
+        chain.append_bytes("!".as_bytes());
+        assert_eq!(chain.pullup_all().unwrap(), "hello!".as_bytes());
+        assert_eq!(shared.peek(5).unwrap(), "hello".as_bytes());
+    }
+
+    #[test]
+    fn test_cloned_chain_is_not_affected_by_appends() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        let mut copy = chain.clone();
+        copy.append_bytes("world".as_bytes());
+        chain.prepend_bytes("oh, ".as_bytes());
+        assert!(chain != copy);
+        assert_eq!(chain.pullup_all().unwrap(), "oh, hello".as_bytes());
+        assert_eq!(copy.pullup_all().unwrap(), "helloworld".as_bytes());
+    }
+
+    struct CountedHolder {
+        data: Vec<u8>,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl DataHolder for CountedHolder {
+        fn get_data(&self, offset: usize, size: usize) -> &[u8] {
+            &self.data[offset..offset + size]
+        }
+
+        fn size(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl Drop for CountedHolder {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn counted_holder(s: &str, drops: &Arc<AtomicUsize>) -> CountedHolder {
+        CountedHolder {
+            data: s.as_bytes().to_vec(),
+            drops: drops.clone(),
+        }
+    }
+
+    #[test]
+    fn test_append_and_prepend_holder_add_data() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut chain = Chain::new();
+        chain.append_bytes("-".as_bytes());
+        chain.append_holder(counted_holder("world", &drops));
+        chain.prepend_holder(counted_holder("hello", &drops));
+        assert_eq!(chain.len(), 11);
+        assert_eq!(chain.copy_bytes_from(0, 11), "hello-world".as_bytes());
+    }
+
+    #[test]
+    fn test_holder_dropped_with_last_node_referencing_it() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut chain = Chain::new();
+        chain.append_holder(counted_holder("helloworld", &drops));
+        let mut other = Chain::new();
+        other.move_from(&mut chain, 5); // node split, holder shared
+        let copy = chain.clone();
+        chain.drain(5);
+        other.reset();
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert_eq!(copy.peek(5).unwrap(), "world".as_bytes());
+        drop(copy);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_sync_chain_accepts_holders() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut chain = SyncChain::default();
+        chain.append_holder(counted_holder("world", &drops));
+        chain.prepend_holder(counted_holder("hello", &drops));
+        let res = thread::spawn(move || chain.copy_bytes_from(0, 10))
+            .join()
+            .unwrap();
+        assert_eq!(&res[..], "helloworld".as_bytes());
+        assert_eq!(drops.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_append_owned_data_does_not_copy() {
+        let mut chain = Chain::new();
+        let v = "hello".as_bytes().to_vec();
+        let vptr = v.as_ptr();
+        let b = "world".as_bytes().to_vec().into_boxed_slice();
+        let bptr = b.as_ptr();
+        let s = String::from("!");
+        let sptr = s.as_ptr();
+        chain.append_vec(v);
+        chain.append_boxed(b);
+        chain.append_string(s);
+        assert_eq!(chain.len(), 11);
+        assert_eq!(chain.pullup_from(0, 5).unwrap().as_ptr(), vptr);
+        assert_eq!(chain.pullup_from(5, 5).unwrap().as_ptr(), bptr);
+        assert_eq!(chain.pullup_from(10, 1).unwrap().as_ptr(), sptr);
+        assert_eq!(chain.pullup_all().unwrap(), "helloworld!".as_bytes());
+    }
+
+    #[test]
+    fn test_append_static_keeps_chain_static() {
+        fn make_chain() -> Chain<'static> {
+            let mut chain = Chain::new();
+            chain.append_static(b"hello");
+            chain.append_vec(b"world".to_vec());
+            chain
+        }
+        let mut chain = make_chain();
+        assert_eq!(chain.pullup_all().unwrap(), "helloworld".as_bytes());
+    }
+
+    fn owned_from_borrowed(drops: &Arc<AtomicUsize>) -> Chain<'static> {
+        let s = String::from("borrowed");
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        chain.append_slice(s.as_bytes());
+        chain.append_holder(counted_holder("world", drops));
+        chain.drain(2);
+        chain.into_owned()
+    }
+
+    #[test]
+    fn test_into_owned_copies_only_borrowed_nodes() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut owned = owned_from_borrowed(&drops);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert_eq!(owned.len(), 16);
+        assert_eq!(owned.pullup_all().unwrap(), "lloborrowedworld".as_bytes());
+        drop(owned);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_to_owned_chain_keeps_source() {
+        let s = String::from("helloworld");
+        let mut chain = Chain::new();
+        chain.append_slice(&s.as_bytes()[..5]);
+        chain.append_slice(&s.as_bytes()[5..]);
+        chain.drain(3);
+        let mut owned = chain.to_owned_chain();
+        assert!(owned == chain);
+        owned.append_bytes("!".as_bytes());
+        assert_eq!(chain.pullup_all().unwrap(), "loworld".as_bytes());
+        drop(chain);
+        drop(s);
+        assert_eq!(owned.pullup_all().unwrap(), "loworld!".as_bytes());
+    }
+
+    fn naive_find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        if needle.is_empty() {
+            return Some(0);
+        }
+        haystack.windows(needle.len()).position(|w| w == needle)
+    }
+
+    fn random_pieces(data: &[u8]) -> Chain<'_> {
+        let mut rng = thread_rng();
+        let mut chain = Chain::new();
+        let mut offs = 0;
+        while offs < data.len() {
+            let l = rng.gen_range(1, 8);
+            let end = std::cmp::min(offs + l, data.len());
+            chain.append_slice(&data[offs..end]);
+            offs = end;
+        }
+        chain
+    }
+
+    #[test]
+    fn test_find_matches_naive_search_across_nodes() {
+        let mut rng = thread_rng();
+        for _ in 0..300usize {
+            let len = rng.gen_range(0, 80);
+            let data: Vec<u8> = (0..len).map(|_| b"ab"[rng.gen_range(0, 2)]).collect();
+            let nlen = rng.gen_range(1, 10);
+            let needle: Vec<u8> = (0..nlen).map(|_| b"ab"[rng.gen_range(0, 2)]).collect();
+            let chain = random_pieces(&data);
+            assert_eq!(chain.find(&needle), naive_find(&data, &needle));
+        }
+    }
+
+    #[test]
+    fn test_find_long_needles_across_nodes_of_any_size() {
+        let mut rng = thread_rng();
+        for _ in 0..300usize {
+            let len = rng.gen_range(0, 300);
+            let data: Vec<u8> = (0..len).map(|_| b"aaB"[rng.gen_range(0, 3)]).collect();
+            let nlen = rng.gen_range(1, 40);
+            let needle: Vec<u8> = (0..nlen).map(|_| b"aab"[rng.gen_range(0, 3)]).collect();
+            let mut chain = Chain::new();
+            let mut offs = 0;
+            while offs < data.len() {
+                let end = std::cmp::min(offs + rng.gen_range(1, 60), data.len());
+                chain.append_slice(&data[offs..end]);
+                offs = end;
+            }
+            assert_eq!(chain.find(&needle), naive_find(&data, &needle));
+            let lower = data.to_ascii_lowercase();
+            assert_eq!(
+                chain.find_ignore_ascii_case(&needle),
+                naive_find(&lower, &needle)
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_long_periodic_needle_in_single_byte_nodes() {
+        let mut data = vec![b'a'; 5000];
+        data.push(b'b');
+        let mut chain = Chain::new();
+        for b in data.chunks(1) {
+            chain.append_slice(b);
+        }
+        let mut needle = vec![b'a'; 299];
+        needle.push(b'b');
+        assert_eq!(chain.find(&needle), Some(5001 - 300));
+        assert_eq!(chain.find_ignore_ascii_case(&needle), Some(5001 - 300));
+        needle.push(b'a');
+        assert_eq!(chain.find(&needle), None);
+    }
+
+    #[test]
+    fn test_find_works_on_binary_data() {
+        let data: Vec<u8> = (0..=255u8).rev().chain(0..=255u8).collect();
+        let chain = random_pieces(&data);
+        assert_eq!(chain.find(&[0xff]), Some(0));
+        assert_eq!(chain.find(&[0x01, 0x00, 0x00, 0x01]), Some(254));
+        assert_eq!(chain.find(&[0xc3, 0x28, 0xa0, 0xa1]), None);
+        assert_eq!(chain.find(&data[100..400]), Some(100));
+    }
+
+    fn naive_match_indices(haystack: &[u8], needle: &[u8]) -> Vec<usize> {
+        let mut res = Vec::new();
+        let mut pos = 0;
+        while let Some(found) = naive_find(&haystack[pos..], needle) {
+            res.push(pos + found);
+            pos += found + needle.len();
+        }
+        res
+    }
+
+    fn naive_rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+        if needle.is_empty() {
+            return Some(haystack.len());
+        }
+        haystack.windows(needle.len()).rposition(|w| w == needle)
+    }
+
+    #[test]
+    fn test_rfind_returns_last_overlapping_occurence() {
+        let mut chain = Chain::new();
+        chain.append_slice("a".as_bytes());
+        chain.append_slice("aa".as_bytes());
+        assert_eq!(chain.rfind(b"aa"), Some(1));
+        assert_eq!(chain.rfind(b"aaa"), Some(0));
+        assert_eq!(chain.rfind(b"aaaa"), None);
+        assert_eq!(chain.rfind(b""), Some(3));
+    }
+
+    #[test]
+    fn test_search_family_matches_naive_search_across_nodes() {
+        let mut rng = thread_rng();
+        for _ in 0..300usize {
+            let len = rng.gen_range(0, 80);
+            let data: Vec<u8> = (0..len).map(|_| b"abAB"[rng.gen_range(0, 4)]).collect();
+            let nlen = rng.gen_range(1, 6);
+            let needle: Vec<u8> = (0..nlen).map(|_| b"ab"[rng.gen_range(0, 2)]).collect();
+            let chain = random_pieces(&data);
+            let all = naive_match_indices(&data, &needle);
+            assert_eq!(chain.match_indices(&needle).collect::<Vec<_>>(), all);
+            assert_eq!(chain.rfind(&needle), naive_rfind(&data, &needle));
+            let from = rng.gen_range(0, len + 1);
+            assert_eq!(
+                chain.find_from(from, &needle),
+                naive_find(&data[from..], &needle).map(|o| o + from)
+            );
+            let lowered = data.to_ascii_lowercase();
+            assert_eq!(
+                chain.find_ignore_ascii_case(&needle.to_ascii_uppercase()),
+                naive_find(&lowered, &needle)
+            );
+            assert_eq!(chain.find_byte(needle[0]), naive_find(&data, &needle[..1]));
+            assert_eq!(
+                chain.find_any_of(b"AB"),
+                data.iter().position(|&b| b == b'A' || b == b'B')
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_from_handles_bounds() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        assert_eq!(chain.find_from(5, b""), Some(5));
+        assert_eq!(chain.find_from(6, b""), None);
+        assert_eq!(chain.find_from(1, b"hello"), None);
+        assert_eq!(chain.find_from(4, b"o"), Some(4));
+        assert_eq!(chain.find_byte(b'x'), None);
+        assert_eq!(chain.find_any_of(b""), None);
+        assert_eq!(chain.match_indices(b"").count(), 6);
+    }
+
+    #[test]
+    fn test_searcher_finds_matches_in_growing_chain() {
+        let mut rng = thread_rng();
+        for _ in 0..100usize {
+            let nlen = rng.gen_range(1, 5);
+            let needle: Vec<u8> = (0..nlen).map(|_| b"ab"[rng.gen_range(0, 2)]).collect();
+            let mut searcher = Searcher::new(&needle);
+            let mut chain = Chain::new();
+            let mut stream = Vec::new();
+            let mut drained = 0;
+            let mut found = Vec::new();
+            for _ in 0..20usize {
+                let l = rng.gen_range(0, 6);
+                let piece: Vec<u8> = (0..l).map(|_| b"ab"[rng.gen_range(0, 2)]).collect();
+                chain.append_bytes(&piece);
+                stream.extend_from_slice(&piece);
+                while let Some(offs) = searcher.next(&chain) {
+                    assert_eq!(chain.copy_bytes_from(offs, nlen), needle);
+                    found.push(drained + offs);
+                }
+                // drain only bytes that can't be part of match anymore
+                let n = rng.gen_range(0, chain.len().saturating_sub(nlen - 1) + 1);
+                chain.drain(n);
+                drained += n;
+            }
+            assert_eq!(found, naive_match_indices(&stream, &needle));
+        }
+    }
+
+    #[test]
+    fn test_searcher_drops_drained_partial_match() {
+        let mut chain = Chain::new();
+        let mut searcher = Searcher::new(b"abab");
+        chain.append_bytes("xxaba".as_bytes());
+        assert_eq!(searcher.next(&chain), None);
+        // "ab" of partial match "aba" is gone, "a" can still start match
+        chain.drain(4);
+        chain.append_bytes("bab".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(0));
+        // everything drained, including scanned part
+        chain.append_bytes("ab".as_bytes());
+        assert_eq!(searcher.next(&chain), None);
+        chain.drain(10);
+        chain.append_bytes("abab".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(0));
+        assert_eq!(searcher.next(&chain), None);
+    }
+
+    #[test]
+    fn test_searcher_reset_starts_from_beginning() {
+        let mut chain = Chain::new();
+        chain.append_bytes("--".as_bytes());
+        chain.prepend_bytes("--".as_bytes());
+        let mut searcher = Searcher::new(b"--");
+        assert_eq!(searcher.next(&chain), Some(0));
+        assert_eq!(searcher.next(&chain), Some(2));
+        assert_eq!(searcher.next(&chain), None);
+        searcher.reset();
+        assert_eq!(searcher.next(&chain), Some(0));
+    }
+
+    #[test]
+    fn test_searcher_keeps_position_after_pullup_from() {
+        let mut chain = Chain::new();
+        chain.append_bytes("ab\r\n".as_bytes());
+        chain.append_slice("cdef".as_bytes());
+        let mut searcher = Searcher::new(b"\r\n");
+        assert_eq!(searcher.next(&chain), Some(2));
+        assert_eq!(chain.pullup_from(1, 5).unwrap(), "b\r\ncd".as_bytes());
+        assert_eq!(searcher.next(&chain), None);
+        chain.append_bytes("\r\n".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(8));
+    }
+
+    #[test]
+    fn test_searcher_rescans_after_tail_removal() {
+        let mut chain = Chain::new();
+        chain.append_bytes("x\r\ny\r\n".as_bytes());
+        let mut searcher = Searcher::new(b"\r\n");
+        assert_eq!(searcher.next(&chain), Some(1));
+        let _ = chain.split_off(2);
+        chain.append_bytes("\r\n".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(2));
+        assert_eq!(searcher.next(&chain), None);
+        // Match cut by truncation can be found again
+        chain.truncate(3);
+        assert_eq!(searcher.next(&chain), None);
+        chain.append_bytes("\n\r\n".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(2));
+        assert_eq!(searcher.next(&chain), Some(4));
+        chain.drain_back(1);
+        chain.append_bytes("\n".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(4));
+        // Data after the last match is scanned again, but not before it
+        chain.append_bytes("ab\r".as_bytes());
+        assert_eq!(searcher.next(&chain), None);
+        chain.drain(2);
+        chain.truncate(5);
+        chain.append_bytes("\r\n".as_bytes());
+        assert_eq!(chain.pullup_all().unwrap(), "\r\n\r\na\r\n".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(5));
+    }
+
+    #[test]
+    fn test_searcher_does_not_repeat_matches_after_cut_below_last_one() {
+        let mut chain = Chain::new();
+        chain.append_bytes("aba".as_bytes());
+        let mut searcher = Searcher::new(b"a");
+        assert_eq!(searcher.next(&chain), Some(0));
+        assert_eq!(searcher.next(&chain), Some(2));
+        chain.truncate(1);
+        assert_eq!(searcher.next(&chain), None);
+        chain.append_bytes("ba".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(2));
+        let _ = chain.split_off(1);
+        assert_eq!(searcher.next(&chain), None);
+        chain.append_bytes("xxa".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(3));
+        chain.remove_range(1..4);
+        assert_eq!(searcher.next(&chain), None);
+        chain.append_bytes("a".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(1));
+    }
+
+    fn naive_find_multi(haystack: &[u8], patterns: &[Vec<u8>]) -> Option<(usize, usize)> {
+        // earliest end, then longest, then first pattern
+        let mut best: Option<(usize, usize, usize)> = None;
+        for (idx, p) in patterns.iter().enumerate() {
+            if let Some(offs) = naive_find(haystack, p) {
+                let key = (offs + p.len(), usize::MAX - p.len(), idx);
+                if best.is_none() || Some(key) < best {
+                    best = Some(key);
+                }
+            }
+        }
+        best.map(|(end, neg_len, idx)| (idx, end - (usize::MAX - neg_len)))
+    }
+
+    #[test]
+    fn test_find_multi_matches_naive_search_across_nodes() {
+        let mut rng = thread_rng();
+        for _ in 0..300usize {
+            let len = rng.gen_range(0, 60);
+            let data: Vec<u8> = (0..len).map(|_| b"abc"[rng.gen_range(0, 3)]).collect();
+            let patterns: Vec<Vec<u8>> = (0..rng.gen_range(1, 5))
+                .map(|_| {
+                    let l = rng.gen_range(1, 5);
+                    (0..l).map(|_| b"abc"[rng.gen_range(0, 3)]).collect()
+                })
+                .collect();
+            let set = PatternSet::new(&patterns);
+            let chain = random_pieces(&data);
+            assert_eq!(chain.find_multi(&set), naive_find_multi(&data, &patterns));
+            let from = rng.gen_range(0, len + 1);
+            assert_eq!(
+                chain.find_multi_from(from, &set),
+                naive_find_multi(&data[from..], &patterns).map(|(i, o)| (i, o + from))
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_multi_handles_empty_sets_and_patterns() {
+        let mut chain = Chain::new();
+        chain.append_bytes("abc".as_bytes());
+        let no_patterns: [&[u8]; 0] = [];
+        assert_eq!(chain.find_multi(&PatternSet::new(no_patterns)), None);
+        let with_empty = PatternSet::new(["c", ""]);
+        assert_eq!(chain.find_multi_from(1, &with_empty), Some((1, 1)));
+        assert_eq!(chain.find_multi_from(4, &with_empty), None);
+        let repeated = PatternSet::new(["b", "b"]);
+        assert_eq!(chain.find_multi(&repeated), Some((0, 1)));
+    }
+
+    #[cfg(feature = "regex")]
+    mod regex_test {
+        use super::random_pieces;
+        use chainbuf::{Chain, Regex};
+
+        #[test]
+        fn test_find_regex_across_nodes() {
+            let data = "foo 123 barbaz 42 qux".as_bytes();
+            let cases: [(&str, Option<(usize, usize)>); 8] = [
+                (r"\d+", Some((4, 7))),
+                (r"bar(baz)?", Some((8, 14))),
+                (r"ba.", Some((8, 11))),
+                (r"(?-u:\b)qux$", Some((18, 21))),
+                (r"^foo", Some((0, 3))),
+                (r"^bar", None),
+                (r"z+", Some((13, 14))),
+                (r"x*", Some((0, 0))),
+            ];
+            for _ in 0..20usize {
+                let chain = random_pieces(data);
+                for &(pattern, expected) in cases.iter() {
+                    let re = Regex::new(pattern).unwrap();
+                    assert_eq!(chain.find_regex(&re), expected, "{}", pattern);
+                }
+            }
+        }
+
+        #[test]
+        fn test_find_regex_from_respects_look_behind() {
+            let data = "ab ab".as_bytes();
+            let re = Regex::new(r"(?-u:\b)b|^a").unwrap();
+            for _ in 0..20usize {
+                let chain = random_pieces(data);
+                assert_eq!(chain.find_regex(&re), Some((0, 1)));
+                // "b" at 1 and "a" at 3 are not at word or text start
+                assert_eq!(chain.find_regex_from(1, &re), None);
+                assert_eq!(chain.find_regex_from(6, &re), None);
+            }
+        }
+
+        #[test]
+        fn test_find_regex_matches_can_be_drained() {
+            let re = Regex::new(r"[0-9]+\n").unwrap();
+            let mut chain = Chain::new();
+            chain.append_bytes("x=10\ny=".as_bytes());
+            chain.append_slice("200\n".as_bytes());
+            let mut values = Vec::new();
+            while let Some((start, end)) = chain.find_regex(&re) {
+                values.push(chain.copy_bytes_from(start, end - start - 1));
+                chain.drain(end);
+            }
+            assert_eq!(values, vec![b"10".to_vec(), b"200".to_vec()]);
+            assert!(chain.is_empty());
+        }
+    }
+
+    fn naive_split(data: &[u8], needle: &[u8], limit: usize) -> Vec<Vec<u8>> {
+        let mut res = Vec::new();
+        let mut pos = 0;
+        while res.len() + 1 < limit {
+            match naive_find(&data[pos..], needle) {
+                Some(found) => {
+                    res.push(data[pos..pos + found].to_vec());
+                    pos += found + needle.len();
+                }
+                None => break,
+            }
+        }
+        if limit > 0 {
+            res.push(data[pos..].to_vec());
+        }
+        res
+    }
+
+    #[test]
+    fn test_split_matches_naive_split() {
+        let mut rng = thread_rng();
+        for _ in 0..300usize {
+            let len = rng.gen_range(0, 40);
+            let data: Vec<u8> = (0..len).map(|_| b"ab\n"[rng.gen_range(0, 3)]).collect();
+            let needle: &[u8] = if rng.gen() { b"\n" } else { b"a\n" };
+            let chain = random_pieces(&data);
+            let parts: Vec<Vec<u8>> = chain
+                .split_on(needle)
+                .map(|p| p.copy_bytes_from(0, p.len()))
+                .collect();
+            assert_eq!(parts, naive_split(&data, needle, usize::MAX));
+            let n = rng.gen_range(0, 5);
+            let parts: Vec<Vec<u8>> = chain
+                .splitn_on(n, needle)
+                .map(|p| p.copy_bytes_from(0, p.len()))
+                .collect();
+            assert_eq!(parts, naive_split(&data, needle, n));
+        }
+    }
+
+    #[test]
+    fn test_split_parts_share_data_holders() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut chain = Chain::new();
+        chain.append_holder(counted_holder("one\ntwo", &drops));
+        chain.append_bytes("\nthree".as_bytes());
+        let mut parts: Vec<Chain> = chain.split_on(b"\n").collect();
+        assert_eq!(parts.len(), 3);
+        drop(chain);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        parts[2].append_bytes("!".as_bytes());
+        assert_eq!(parts[2].pullup_all().unwrap(), "three!".as_bytes());
+        assert_eq!(parts[0].pullup_all().unwrap(), "one".as_bytes());
+        assert_eq!(parts[1].pullup_all().unwrap(), "two".as_bytes());
+        parts.clear();
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    fn naive_lines(data: &[u8]) -> Vec<Vec<u8>> {
+        let mut res = Vec::new();
+        let mut pos = 0;
+        while let Some(nl) = naive_find(&data[pos..], b"\n") {
+            let mut line = &data[pos..pos + nl];
+            if line.last() == Some(&b'\r') {
+                line = &line[..line.len() - 1];
+            }
+            res.push(line.to_vec());
+            pos += nl + 1;
+        }
+        res
+    }
+
+    #[test]
+    fn test_chain_lines_and_next_line_match_naive_lines() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0, 64);
+            let data: Vec<u8> = (0..len).map(|_| *rng.choose(b"ab\r\n").unwrap()).collect();
+            let expected = naive_lines(&data);
+            let mut chain = random_pieces(&data);
+            let lines: Vec<Vec<u8>> = chain
+                .chain_lines()
+                .map(|l| l.copy_bytes_from(0, l.len()))
+                .collect();
+            assert_eq!(lines, expected);
+            let mut read = Vec::new();
+            while let Some(l) = chain.next_line(None).unwrap() {
+                read.push(l.copy_bytes_from(0, l.len()));
+            }
+            assert_eq!(read, expected);
+            assert!(chain.find("\n".as_bytes()).is_none());
+        }
+    }
+
+    #[test]
+    fn test_next_line_max_length() {
+        let mut chain = Chain::new();
+        chain.append_bytes("abcd\r".as_bytes());
+        assert!(chain.next_line(Some(4)).unwrap().is_none());
+        chain.append_bytes("\nabcde\n".as_bytes());
+        assert_eq!(chain.next_line(Some(4)).unwrap().unwrap().len(), 4);
+        let err = chain.next_line(Some(4)).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(chain.len(), 6);
+        assert_eq!(chain.next_line(None).unwrap().unwrap().len(), 5);
+        assert!(chain.is_empty());
+    }
+
+    #[test]
+    fn test_bufread_line_methods_are_not_shadowed() {
+        let mut chain = Chain::new();
+        chain.append_bytes("one\ntwo\nthree".as_bytes());
+        assert_eq!(chain.chain_lines().count(), 2);
+        let mut line = String::new();
+        assert_eq!(chain.read_line(&mut line).unwrap(), 4);
+        assert_eq!(line, "one\n");
+        let mut buf = Vec::new();
+        assert_eq!(chain.read_until(b'\n', &mut buf).unwrap(), 4);
+        let lines: Vec<String> = chain.lines().map(|l| l.unwrap()).collect();
+        assert_eq!(lines, vec!["three"]);
+    }
+
+    #[test]
+    fn test_next_until_max_length() {
+        let mut chain = Chain::new();
+        chain.append_bytes("abc\r\n\r".as_bytes());
+        let data = chain.next_until("\r\n\r\n".as_bytes(), Some(3)).unwrap();
+        assert!(data.is_none());
+        chain.append_bytes("\n".as_bytes());
+        let data = chain
+            .next_until("\r\n\r\n".as_bytes(), Some(3))
+            .unwrap()
+            .unwrap();
+        assert_eq!(data.copy_bytes_from(0, data.len()), b"abc\r\n\r\n".to_vec());
+        chain.append_bytes("abcd\r\n".as_bytes());
+        assert!(chain.next_until("\r\n".as_bytes(), Some(3)).is_err());
+        chain.reset();
+        chain.append_bytes("abcd\r".as_bytes());
+        assert!(chain.next_until("\r\n".as_bytes(), Some(3)).is_err());
+        assert_eq!(chain.len(), 5);
+    }
+
+    #[test]
+    fn test_split_to_and_split_off_random_offsets() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0, 64);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen::<u8>()).collect();
+            let at = rng.gen_range(0, len + 3);
+            let split = std::cmp::min(at, data.len());
+            let mut chain = random_pieces(&data);
+            let front = chain.split_to(at);
+            assert_eq!(front.copy_bytes_from(0, front.len()), &data[..split]);
+            assert_eq!(chain.copy_bytes_from(0, chain.len()), &data[split..]);
+            let mut chain = random_pieces(&data);
+            let tail = chain.split_off(at);
+            assert_eq!(chain.copy_bytes_from(0, chain.len()), &data[..split]);
+            assert_eq!(tail.copy_bytes_from(0, tail.len()), &data[split..]);
+        }
+    }
+
+    #[test]
+    fn test_split_off_does_not_overwrite_shared_data() {
+        let mut chain = Chain::new();
+        chain.append_bytes("helloworld".as_bytes());
+        let mut tail = chain.split_off(5);
+        chain.append_bytes("there".as_bytes());
+        assert_eq!(chain.pullup_all().unwrap(), "hellothere".as_bytes());
+        assert_eq!(tail.pullup_all().unwrap(), "world".as_bytes());
+    }
+
+    #[test]
+    fn test_truncate_and_drain_back() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0, 64);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen::<u8>()).collect();
+            let n = rng.gen_range(0, len + 3);
+            let left = data.len().saturating_sub(n);
+            let mut chain = random_pieces(&data);
+            chain.drain_back(n);
+            assert_eq!(chain.len(), left);
+            assert_eq!(chain.copy_bytes_from(0, chain.len()), &data[..left]);
+            chain.truncate(left / 2);
+            assert_eq!(chain.copy_bytes_from(0, chain.len()), &data[..left / 2]);
+            chain.append_bytes("end".as_bytes());
+            assert_eq!(chain.copy_bytes_from(left / 2, 3), "end".as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_slice_random_ranges() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0, 64);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen::<u8>()).collect();
+            let to = rng.gen_range(0, len + 1);
+            let from = rng.gen_range(0, to + 1);
+            let chain = random_pieces(&data);
+            let s = chain.slice(from..to);
+            assert_eq!(s.copy_bytes_from(0, s.len()), &data[from..to]);
+            let s = chain.slice(from..);
+            assert_eq!(s.copy_bytes_from(0, s.len()), &data[from..]);
+            let s = chain.slice(..to);
+            assert_eq!(s.copy_bytes_from(0, s.len()), &data[..to]);
+            assert!(chain.slice(..) == chain);
+            assert_eq!(chain.len(), data.len());
+        }
+    }
+
+    #[test]
+    fn test_slice_shares_data_holders() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut chain = Chain::new();
+        chain.append_bytes("head".as_bytes());
+        chain.append_holder(counted_holder("body", &drops));
+        let mut body = chain.slice(5..=6);
+        drop(chain);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert_eq!(body.pullup_all().unwrap(), "od".as_bytes());
+        drop(body);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_slice_out_of_bounds() {
+        let mut chain = Chain::new();
+        chain.append_bytes("hello".as_bytes());
+        chain.slice(2..6);
+    }
+
+    #[test]
+    fn test_splice_random_edits() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0, 64);
+            let data: Vec<u8> = (0..len).map(|_| rng.gen::<u8>()).collect();
+            let mut expected = data.clone();
+            let mut chain = random_pieces(&data);
+            for _ in 0..4 {
+                let to = rng.gen_range(0, expected.len() + 1);
+                let from = rng.gen_range(0, to + 1);
+                let n = rng.gen_range(0, 8);
+                let new: Vec<u8> = (0..n).map(|_| rng.gen::<u8>()).collect();
+                let op = rng.gen_range(0, 4);
+                match op {
+                    0 => chain.insert_bytes(from, &new),
+                    1 => chain.insert_chain(from, random_pieces(&new).into_owned()),
+                    2 => chain.remove_range(from..to),
+                    _ => chain.splice(from..to, random_pieces(&new).into_owned()),
+                }
+                match op {
+                    0 | 1 => expected.splice(from..from, new),
+                    2 => expected.splice(from..to, vec![]),
+                    _ => expected.splice(from..to, new),
+                };
+                assert_eq!(chain.len(), expected.len());
+                assert_eq!(chain.copy_bytes_from(0, chain.len()), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_splice_keeps_shared_data_intact() {
+        let mut chain = Chain::new();
+        chain.append_bytes("helloworld".as_bytes());
+        let copy = chain.clone();
+        chain.remove_range(2..8);
+        chain.append_bytes("!".as_bytes());
+        chain.insert_bytes(2, "-".as_bytes());
+        assert_eq!(chain.pullup_all().unwrap(), "he-ld!".as_bytes());
+        assert_eq!(copy.copy_bytes_from(0, 10), "helloworld".as_bytes());
+    }
+
+    #[test]
+    fn test_searcher_after_splice_at_head() {
+        let mut chain = Chain::new();
+        chain.append_bytes("xxab".as_bytes());
+        let mut searcher = Searcher::new("abc".as_bytes());
+        assert_eq!(searcher.next(&chain), None);
+        chain.splice(..2, Chain::new());
+        chain.append_bytes("c".as_bytes());
+        assert_eq!(searcher.next(&chain), Some(0));
+    }
+
+    #[test]
+    fn test_binary_get_across_nodes() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let a: u16 = rng.gen();
+            let b: i32 = rng.gen();
+            let c: u64 = rng.gen();
+            let d = (i128::from(rng.gen::<i64>()) << 64) | i128::from(rng.gen::<u64>());
+            let e = rng.gen::<f64>();
+            let mut data = Vec::new();
+            data.extend_from_slice(&a.to_le_bytes());
+            data.extend_from_slice(&b.to_be_bytes());
+            data.extend_from_slice(&c.to_le_bytes());
+            data.extend_from_slice(&d.to_be_bytes());
+            data.extend_from_slice(&e.to_be_bytes());
+            data.push(0xff);
+            let mut chain = random_pieces(&data);
+            assert_eq!(chain.peek_u16_le(), Some(a));
+            assert_eq!(chain.get_u16_le(), Some(a));
+            assert_eq!(chain.get_i32_be(), Some(b));
+            assert_eq!(chain.get_u64_le(), Some(c));
+            assert_eq!(chain.get_i128_be(), Some(d));
+            assert_eq!(chain.get_f64_be(), Some(e));
+            assert_eq!(chain.get_u16_be(), None);
+            assert_eq!(chain.get_i8(), Some(-1));
+            assert!(chain.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_binary_put_writes_to_tail_room() {
+        let mut chain = Chain::new();
+        chain.reserve(64);
+        chain.put_u32_be(0x01020304);
+        chain.put_i16_le(-2);
+        chain.put_f32_le(1.5);
+        chain.put_u8(7);
+        let mut expected = vec![1, 2, 3, 4, 0xfe, 0xff];
+        expected.extend_from_slice(&1.5f32.to_le_bytes());
+        expected.push(7);
+        assert_eq!(chain.pullup(11).unwrap(), &expected[..]);
+        assert_eq!(chain.get_u32_be(), Some(0x01020304));
+        assert_eq!(chain.get_i16_le(), Some(-2));
+        assert_eq!(chain.get_f32_le(), Some(1.5));
+    }
+
+    #[test]
+    fn test_varint_round_trip() {
+        let values = [0, 1, 127, 128, 300, 16383, 16384, u64::MAX - 1, u64::MAX];
+        let mut chain = Chain::new();
+        for &v in values.iter() {
+            chain.put_varint(v);
+        }
+        let data = chain.copy_bytes_from(0, chain.len());
+        let mut chain = random_pieces(&data);
+        for &v in values.iter() {
+            assert_eq!(chain.get_varint().unwrap(), Some(v));
+        }
+        assert!(chain.is_empty());
+        for &v in [0, -1, 1, i64::MIN, i64::MAX].iter() {
+            chain.put_varint_i64(v);
+            assert_eq!(chain.get_varint_i64().unwrap(), Some(v));
+        }
+    }
+
+    #[test]
+    fn test_varint_incomplete_and_too_long() {
+        let mut chain = Chain::new();
+        chain.append_bytes(&[0xff; 9]);
+        assert_eq!(chain.get_varint().unwrap(), None);
+        chain.append_bytes(&[0x01]);
+        assert_eq!(chain.peek_varint().unwrap(), Some((u64::MAX, 10)));
+        chain.reset();
+        chain.append_bytes(&[0xff; 9]);
+        chain.append_bytes(&[0x02]);
+        assert!(chain.get_varint().is_err());
+        assert_eq!(chain.len(), 10);
+    }
+
+    // Reader returning data in small random pieces
+    struct Trickle {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    impl Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = *[1, 2, 3, 5]
+                .iter()
+                .min_by_key(|_| thread_rng().gen::<u8>())
+                .unwrap();
+            let n = std::cmp::min(std::cmp::min(n, buf.len()), self.data.len() - self.pos);
+            buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+            self.pos += n;
+            Ok(n)
+        }
+    }
+
+    fn random_frames() -> Vec<Vec<u8>> {
+        let mut rng = thread_rng();
+        (0..rng.gen_range(0, 20))
+            .map(|_| {
+                let len = rng.gen_range(0, 300);
+                (0..len).map(|_| *rng.choose(b"ab\r\n").unwrap()).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_framed_length_delimited_round_trip() {
+        for _ in 0..20 {
+            let frames = random_frames();
+            let mut out = Vec::new();
+            {
+                let codec = LengthDelimitedCodec::new().length_field_length(2);
+                let mut framed = Framed::new(&mut out, codec);
+                for f in frames.iter() {
+                    framed.write_frame(&f[..]).unwrap();
+                }
+                framed.flush().unwrap();
+            }
+            let rd = Trickle { data: out, pos: 0 };
+            let codec = LengthDelimitedCodec::new().length_field_length(2);
+            let decoded: Vec<Vec<u8>> = Framed::new(rd, codec)
+                .map(|f| {
+                    let f = f.unwrap();
+                    f.copy_bytes_from(0, f.len())
+                })
+                .collect();
+            assert_eq!(decoded, frames);
+        }
+    }
+
+    // Reader returning data byte by byte
+    struct ByteByByte(io::Cursor<Vec<u8>>);
+
+    impl Read for ByteByByte {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = std::cmp::min(buf.len(), 1);
+            self.0.read(&mut buf[..n])
+        }
+    }
+
+    #[test]
+    fn test_framed_single_byte_reads_fill_tail_node() {
+        let frames = [vec![b'a'; 3000], vec![b'b'; 3000]];
+        let mut out = Vec::new();
+        {
+            let mut framed = Framed::new(&mut out, LengthDelimitedCodec::new());
+            for f in frames.iter() {
+                framed.write_frame(&f[..]).unwrap();
+            }
+            framed.flush().unwrap();
+        }
+        let rd = ByteByByte(io::Cursor::new(out));
+        let mut framed = Framed::new(rd, LengthDelimitedCodec::new());
+        for f in frames.iter() {
+            let frame = framed.read_frame().unwrap().unwrap();
+            assert_eq!(frame.copy_bytes_from(0, frame.len()), *f);
+            assert_eq!(segment_ptrs(&frame).len(), 1);
+        }
+        assert!(framed.read_frame().unwrap().is_none());
+        assert!(framed.read_buffer().is_empty());
+    }
+
+    #[test]
+    fn test_framed_delimiter_round_trip() {
+        for _ in 0..20 {
+            let frames: Vec<Vec<u8>> = random_frames()
+                .into_iter()
+                .map(|f| f.into_iter().filter(|&b| b != b'\n').collect())
+                .collect();
+            let mut out = Vec::new();
+            Framed::new(&mut out, DelimiterCodec::new(b"\r\n"))
+                .send(&b""[..])
+                .unwrap();
+            let mut data = Vec::new();
+            for f in frames.iter() {
+                data.extend_from_slice(f);
+                data.extend_from_slice(b"\r\n");
+            }
+            let rd = Trickle { data, pos: 0 };
+            let decoded: Vec<Vec<u8>> = Framed::new(rd, DelimiterCodec::new(b"\r\n"))
+                .map(|f| {
+                    let f = f.unwrap();
+                    f.copy_bytes_from(0, f.len())
+                })
+                .collect();
+            assert_eq!(decoded, frames);
+            assert_eq!(out, b"\r\n".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_codecs_max_frame_length() {
+        let mut codec = DelimiterCodec::new(b"\r\n").max_frame_length(3);
+        let mut chain = Chain::new();
+        chain.append_bytes("abc\r".as_bytes());
+        assert!(codec.decode(&mut chain).unwrap().is_none());
+        chain.append_bytes("\nabcde".as_bytes());
+        assert_eq!(codec.decode(&mut chain).unwrap().unwrap().len(), 3);
+        assert!(codec.decode(&mut chain).is_err());
+        assert!(codec.encode(&b"abcd"[..], &mut chain).is_err());
+
+        let mut codec = LengthDelimitedCodec::new().max_frame_length(3);
+        let mut chain = Chain::new();
+        chain.append_bytes(&[0, 0, 0, 4]);
+        assert_eq!(
+            codec.decode(&mut chain).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert!(codec.encode(&b"abcd"[..], &mut chain).is_err());
+        let mut codec = LengthDelimitedCodec::new().length_field_length(1);
+        assert!(codec.encode(&[0u8; 256][..], &mut chain).is_err());
+        assert_eq!(chain.len(), 4);
+    }
+
+    #[test]
+    fn test_delimiter_codec_decode_eof_returns_rest() {
+        let mut codec = DelimiterCodec::new(b"\n");
+        let mut chain = Chain::new();
+        chain.append_bytes("a\nb".as_bytes());
+        assert_eq!(codec.decode_eof(&mut chain).unwrap().unwrap().len(), 1);
+        assert_eq!(codec.decode_eof(&mut chain).unwrap().unwrap().len(), 1);
+        assert!(codec.decode_eof(&mut chain).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_chunked_round_trip_in_random_pieces() {
+        let mut rng = thread_rng();
+        for _ in 0..50 {
+            let frames = random_frames();
+            let trailers: &[u8] = if rng.gen() {
+                b""
+            } else {
+                b"Expires: 0\r\nX-A: b\r\n"
+            };
+            let mut encoded = Chain::new();
+            for f in frames.iter() {
+                let chunk = Chunk::Data(random_pieces(f));
+                ChunkedEncoder.encode(chunk, &mut encoded).unwrap();
+            }
+            let mut end = Chain::new();
+            end.append_bytes(trailers);
+            ChunkedEncoder
+                .encode(Chunk::End(end), &mut encoded)
+                .unwrap();
+            encoded.append_bytes("GET /".as_bytes());
+            let data = encoded.copy_bytes_from(0, encoded.len());
+
+            let mut decoder = ChunkedDecoder::new();
+            let mut chain = Chain::new();
+            let mut body = Vec::new();
+            let mut got_trailers = None;
+            for piece in data.chunks(rng.gen_range(1, 16)) {
+                chain.append_bytes(piece);
+                while let Some(chunk) = decoder.decode(&mut chain).unwrap() {
+                    match chunk {
+                        Chunk::Data(d) => body.extend(d.copy_bytes_from(0, d.len())),
+                        Chunk::End(t) => got_trailers = Some(t.copy_bytes_from(0, t.len())),
+                    }
+                }
+            }
+            assert!(decoder.is_done());
+            assert_eq!(body, frames.concat());
+            assert_eq!(got_trailers.unwrap(), trailers);
+            assert_eq!(chain.copy_bytes_from(0, chain.len()), b"GET /".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_chunked_decoder_shares_payload() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut chain = Chain::new();
+        chain.append_bytes("5\r\n".as_bytes());
+        chain.append_holder(counted_holder("hello", &drops));
+        chain.append_bytes("\r\n0\r\n\r\n".as_bytes());
+        let mut decoder = ChunkedDecoder::new();
+        let chunk = decoder.decode(&mut chain).unwrap();
+        let mut data = match chunk {
+            Some(Chunk::Data(d)) => d,
+            _ => panic!("expected data"),
+        };
+        assert!(matches!(
+            decoder.decode(&mut chain).unwrap(),
+            Some(Chunk::End(_))
+        ));
+        drop(chain);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert_eq!(data.pullup_all().unwrap(), "hello".as_bytes());
+    }
+
+    #[test]
+    fn test_chunked_decoder_errors() {
+        for bad in ["x\r\n", "\r\n", "5\r\nhelloXX", "fffffffffffffffff\r\n"].iter() {
+            let mut chain = Chain::new();
+            chain.append_bytes(bad.as_bytes());
+            let mut decoder = ChunkedDecoder::new();
+            let res = (0..3).try_for_each(|_| decoder.decode(&mut chain).map(|_| ()));
+            assert_eq!(res.err().unwrap().kind(), io::ErrorKind::InvalidData);
+        }
+        let mut chain = Chain::new();
+        chain.append_bytes(&[b'1'; 2000]);
+        assert!(ChunkedDecoder::new().decode(&mut chain).is_err());
+        let mut chain = Chain::new();
+        chain.append_bytes("5\r\nhel".as_bytes());
+        let mut decoder = ChunkedDecoder::new();
+        assert!(decoder.decode_eof(&mut chain).unwrap().is_some());
+        let err = decoder.decode_eof(&mut chain).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_head_codec_parses_request_in_random_pieces() {
+        let data = b"\r\nPOST /a?b=c HTTP/1.0\r\nHost:x\r\nX-Empty: \r\nX-Tab:\t v w \r\n\r\nbody";
+        for _ in 0..50 {
+            let mut chain = random_pieces(data);
+            let head = HeadCodec::new().decode(&mut chain).unwrap().unwrap();
+            assert!(!head.is_response());
+            assert_eq!(&head.method().unwrap()[..], b"POST");
+            assert_eq!(&head.target().unwrap()[..], b"/a?b=c");
+            assert_eq!(&head.version()[..], b"HTTP/1.0");
+            assert_eq!(head.status(), None);
+            let headers: Vec<(Vec<u8>, Vec<u8>)> = head
+                .headers()
+                .map(|(n, v)| (n.to_vec(), v.to_vec()))
+                .collect();
+            assert_eq!(
+                headers,
+                vec![
+                    (b"Host".to_vec(), b"x".to_vec()),
+                    (b"X-Empty".to_vec(), vec![]),
+                    (b"X-Tab".to_vec(), b"v w".to_vec()),
+                ]
+            );
+            let (name, value) = head.header_offsets(2).unwrap();
+            assert_eq!(head.raw().copy_bytes_from(name.start, name.len()), b"X-Tab");
+            assert_eq!(head.raw().copy_bytes_from(value.start, value.len()), b"v w");
+            assert_eq!(chain.copy_bytes_from(0, chain.len()), b"body".to_vec());
+        }
+    }
+
+    #[test]
+    fn test_head_codec_parses_response() {
+        let mut chain = Chain::new();
+        chain.append_bytes("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".as_bytes());
+        let head = HeadCodec::new().decode(&mut chain).unwrap().unwrap();
+        assert!(head.is_response());
+        assert_eq!(head.status(), Some(404));
+        assert_eq!(&head.reason().unwrap()[..], b"Not Found");
+        assert!(head.method().is_none());
+        assert_eq!(&head.header(b"content-length").unwrap()[..], b"0");
+        assert!(chain.is_empty());
+    }
+
+    #[test]
+    fn test_head_codec_errors() {
+        let bad = [
+            "GET /\r\n\r\n",
+            "GET / HTTP/2.0\r\n\r\n",
+            "G@T / HTTP/1.1\r\n\r\n",
+            "HTTP/1.1 20 OK\r\n\r\n",
+            "HTTP/1.1 099 X\r\n\r\n",
+            "GET / HTTP/1.1\r\nNo colon\r\n\r\n",
+            "GET / HTTP/1.1\r\n folded: x\r\n\r\n",
+            "GET / HTTP/1.1\r\nA: b\0c\r\n\r\n",
+        ];
+        for b in bad.iter() {
+            let mut chain = Chain::new();
+            chain.append_bytes(b.as_bytes());
+            assert!(HeadCodec::new().decode(&mut chain).is_err(), "{:?}", b);
+        }
+        let mut codec = HeadCodec::new().max_head_length(16);
+        let mut chain = Chain::new();
+        chain.append_bytes("GET / HTTP/1.1\r".as_bytes());
+        assert!(codec.decode(&mut chain).unwrap().is_none());
+        chain.append_bytes("\n".as_bytes());
+        assert!(codec.decode(&mut chain).is_err());
+    }
+
+    #[test]
+    fn test_head_codec_head_at_max_length() {
+        let data = b"GET / HTTP/1.1\r\nA: b\r\n\r\n";
+        let mut codec = HeadCodec::new().max_head_length(data.len());
+        for _ in 0..20 {
+            let mut chain = random_pieces(data);
+            assert!(codec.decode(&mut chain).unwrap().is_some());
+        }
+        // Fed byte by byte, head is incomplete until its last byte
+        let mut chain = Chain::new();
+        for &b in data[..data.len() - 1].iter() {
+            chain.append_bytes(&[b]);
+            assert!(codec.decode(&mut chain).unwrap().is_none());
+        }
+        chain.append_bytes(&data[data.len() - 1..]);
+        assert!(codec.decode(&mut chain).unwrap().is_some());
+        assert!(chain.is_empty());
+        // One byte longer head fails as soon as it can't fit
+        let data = b"GET / HTTP/1.1\r\nA: bc\r\n\r\n";
+        let mut chain = Chain::new();
+        for &b in data[..data.len() - 2].iter() {
+            chain.append_bytes(&[b]);
+            assert!(codec.decode(&mut chain).unwrap().is_none());
+        }
+        chain.append_bytes(&data[data.len() - 2..data.len() - 1]);
+        assert!(codec.decode(&mut chain).is_err());
+    }
+
+    #[test]
+    fn test_head_edit_and_serialize() {
+        let mut chain = Chain::new();
+        chain.append_bytes("GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\na: 3\r\nC: 4\r\n\r\n".as_bytes());
+        let mut head: Head = HeadCodec::new().decode(&mut chain).unwrap().unwrap();
+        assert_eq!(head.remove_header(b"A"), 2);
+        head.append_header(b"Via", b"proxy").unwrap();
+        assert!(head.append_header(b"X", b"a\r\nInjected: 1").is_err());
+        assert!(head.append_header(b"Bad Name", b"a").is_err());
+        assert_eq!(&head.header(b"c").unwrap()[..], b"4");
+        let mut out = Chain::new();
+        HeadCodec::new().encode(head.clone(), &mut out).unwrap();
+        assert_eq!(
+            out.pullup_all().unwrap(),
+            "GET / HTTP/1.1\r\nB: 2\r\nC: 4\r\nVia: proxy\r\n\r\n".as_bytes()
+        );
+        let mut req: Head = Head::request("PUT", "/x");
+        req.append_header(b"Host", b"h").unwrap();
+        let mut out = req.into_chain();
+        let parsed = HeadCodec::new().decode(&mut out).unwrap().unwrap();
+        assert_eq!(&parsed.method().unwrap()[..], b"PUT");
+        assert_eq!(&parsed.header(b"host").unwrap()[..], b"h");
+    }
+
+    fn show_resp(v: &Value) -> String {
+        let s = |c: &Chain| String::from_utf8(c.copy_bytes_from(0, c.len())).unwrap();
+        let list = |items: &[Value]| items.iter().map(show_resp).collect::<Vec<_>>().join(",");
+        let pairs = |items: &[(Value, Value)]| {
+            items
+                .iter()
+                .map(|(k, v)| format!("{}={}", show_resp(k), show_resp(v)))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        match v {
+            Value::SimpleString(c) => format!("+{}", s(c)),
+            Value::Error(c) => format!("-{}", s(c)),
+            Value::Integer(n) => format!(":{}", n),
+            Value::BulkString(c) => format!("${}", s(c)),
+            Value::Array(items) => format!("[{}]", list(items)),
+            Value::Null => "null".to_string(),
+            Value::Boolean(b) => format!("#{}", b),
+            Value::Double(d) => format!(",{}", d),
+            Value::BigNumber(c) => format!("({}", s(c)),
+            Value::BulkError(c) => format!("!{}", s(c)),
+            Value::VerbatimString(c) => format!("={}", s(c)),
+            Value::Map(items) => format!("{{{}}}", pairs(items)),
+            Value::Set(items) => format!("~[{}]", list(items)),
+            Value::Attribute(attrs, v) => format!("|{{{}}}{}", pairs(attrs), show_resp(v)),
+            Value::Push(items) => format!(">[{}]", list(items)),
+        }
+    }
+
+    fn segment_ptrs(chain: &Chain) -> Vec<*const u8> {
+        let mut chain = chain.clone();
+        let mut ptrs = Vec::new();
+        while !chain.is_empty() {
+            let n = {
+                let data = chain.fill_buf().unwrap();
+                ptrs.push(data.as_ptr());
+                data.len()
+            };
+            chain.consume(n);
+        }
+        ptrs
+    }
+
+    const RESP_REPLIES: &[(&str, &str)] = &[
+        ("+OK\r\n", "+OK"),
+        ("-ERR bad\r\n", "-ERR bad"),
+        (":-42\r\n", ":-42"),
+        ("$5\r\nhe\r\no\r\n", "$he\r\no"),
+        ("$0\r\n\r\n", "$"),
+        ("$-1\r\n", "null"),
+        ("*-1\r\n", "null"),
+        ("*0\r\n", "[]"),
+        ("*3\r\n:1\r\n*1\r\n+a\r\n$1\r\nb\r\n", "[:1,[+a],$b]"),
+        ("_\r\n", "null"),
+        ("#t\r\n", "#true"),
+        (",-1.5\r\n", ",-1.5"),
+        (",inf\r\n", ",inf"),
+        (
+            "(3492890328409238509324850943850943825024385\r\n",
+            "(3492890328409238509324850943850943825024385",
+        ),
+        ("!21\r\nSYNTAX invalid syntax\r\n", "!SYNTAX invalid syntax"),
+        ("=15\r\ntxt:Some string\r\n", "=txt:Some string"),
+        ("%2\r\n+a\r\n:1\r\n$1\r\nb\r\n#f\r\n", "{+a=:1,$b=#false}"),
+        ("~2\r\n+a\r\n_\r\n", "~[+a,null]"),
+        ("|1\r\n+ttl\r\n:3600\r\n$3\r\nval\r\n", "|{+ttl=:3600}$val"),
+        (">2\r\n+message\r\n$2\r\nhi\r\n", ">[+message,$hi]"),
+    ];
+
+    #[test]
+    fn test_resp_decodes_replies_in_random_pieces() {
+        let mut rng = thread_rng();
+        let data = RESP_REPLIES.iter().map(|r| r.0).collect::<String>();
+        for _ in 0..50 {
+            let mut codec = RespCodec::new();
+            let mut chain = Chain::new();
+            let mut got = Vec::new();
+            for piece in data.as_bytes().chunks(rng.gen_range(1, 16)) {
+                chain.append_bytes(piece);
+                while let Some(v) = codec.decode(&mut chain).unwrap() {
+                    got.push(show_resp(&v));
+                }
+            }
+            assert!(chain.is_empty());
+            let expected = RESP_REPLIES.iter().map(|r| r.1).collect::<Vec<_>>();
+            assert_eq!(got, expected);
+        }
+    }
+
+    #[test]
+    fn test_resp_decoder_waits_for_complete_reply() {
+        let data = b"*2\r\n$3\r\nfoo\r\n%1\r\n+k\r\n:7\r\n";
+        let mut codec = RespCodec::new();
+        for n in 0..data.len() {
+            let mut chain = random_pieces(&data[..n]);
+            assert!(codec.decode(&mut chain).unwrap().is_none());
+            assert_eq!(chain.len(), n);
+        }
+        let mut chain = random_pieces(data);
+        let v = codec.decode(&mut chain).unwrap().unwrap();
+        assert_eq!(show_resp(&v), "[$foo,{+k=:7}]");
+        assert!(chain.is_empty());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // too slow under Miri
+    fn test_resp_decodes_large_array_in_pieces() {
+        // Each piece is checked once, so this doesn't take quadratic time
+        let n = 100_000;
+        let mut data = format!("*{}\r\n", n).into_bytes();
+        for i in 0..n {
+            match i % 3 {
+                0 => data.extend_from_slice(format!(":{}\r\n", i).as_bytes()),
+                1 => {
+                    let bulk = "x".repeat(i % 10);
+                    data.extend_from_slice(format!("${}\r\n{}\r\n", bulk.len(), bulk).as_bytes())
+                }
+                _ => data.extend_from_slice(b"*2\r\n+a\r\n_\r\n"),
+            }
+        }
+        data.extend_from_slice(b"+OK\r\n");
+        let mut codec = RespCodec::new();
+        let mut chain = Chain::new();
+        let mut got = Vec::new();
+        for piece in data.chunks(100) {
+            chain.append_bytes(piece);
+            while let Some(v) = codec.decode(&mut chain).unwrap() {
+                got.push(v);
+            }
+        }
+        assert!(chain.is_empty());
+        assert_eq!(got.len(), 2);
+        match &got[0] {
+            Value::Array(items) => {
+                assert_eq!(items.len(), n);
+                assert_eq!(show_resp(&items[n - 1]), ":99999");
+                assert_eq!(show_resp(&items[n - 2]), "[+a,null]");
+            }
+            _ => panic!("expected array"),
+        }
+        assert_eq!(show_resp(&got[1]), "+OK");
+    }
+
+    #[test]
+    fn test_resp_decoder_shares_bulk_strings() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let mut chain = Chain::new();
+        chain.append_bytes("$5\r\n".as_bytes());
+        chain.append_holder(counted_holder("hello", &drops));
+        chain.append_bytes("\r\n".as_bytes());
+        let data = match RespCodec::new().decode(&mut chain).unwrap() {
+            Some(Value::BulkString(d)) => d,
+            _ => panic!("expected bulk string"),
+        };
+        drop(chain);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        assert_eq!(data.copy_bytes_from(0, data.len()), b"hello".to_vec());
+        drop(data);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_resp_decoder_errors() {
+        let bad = [
+            "?x\r\n",
+            "\r\n",
+            ":12a\r\n",
+            "#x\r\n",
+            "_1\r\n",
+            "$-2\r\n",
+            "$3\r\nabcd\r\n",
+            "*-2\r\n",
+            "%-1\r\n",
+            "$?\r\n",
+        ];
+        for bad in bad.iter() {
+            let mut chain = Chain::new();
+            chain.append_bytes(bad.as_bytes());
+            let err = RespCodec::new().decode(&mut chain).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(chain.len(), bad.len());
+        }
+        let mut chain = Chain::new();
+        chain.append_bytes("$11\r\n".as_bytes());
+        let mut codec = RespCodec::new().max_bulk_length(10);
+        assert!(codec.decode(&mut chain).is_err());
+        let mut chain = Chain::new();
+        chain.append_bytes(&[b'+'; 100 * 1024]);
+        assert!(codec.decode(&mut chain).is_err());
+        let mut chain = Chain::new();
+        for _ in 0..100 {
+            chain.append_bytes("*1\r\n".as_bytes());
+        }
+        chain.append_bytes(":1\r\n".as_bytes());
+        assert!(codec.decode(&mut chain).is_err());
+    }
+
+    #[test]
+    fn test_resp_encoder_does_not_copy_values() {
+        let borrowed = vec![b'b'; 1000];
+        let owned = vec![b'o'; 1000];
+        let owned_ptr = owned.as_ptr();
+        let mut chained = Chain::new();
+        chained.append_slice(&borrowed[..10]);
+        let cmd = Command::new("MSET")
+            .arg(b"k1")
+            .arg_slice(&borrowed)
+            .arg("k2")
+            .arg_vec(owned)
+            .arg_chain(chained);
+        let mut dst = Chain::new();
+        RespCodec::new().encode(cmd, &mut dst).unwrap();
+        let ptrs = segment_ptrs(&dst);
+        assert!(ptrs.contains(&borrowed.as_ptr()));
+        assert!(ptrs.contains(&owned_ptr));
+        let mut expected = b"*6\r\n$4\r\nMSET\r\n$2\r\nk1\r\n$1000\r\n".to_vec();
+        expected.extend_from_slice(&borrowed);
+        expected.extend_from_slice(b"\r\n$2\r\nk2\r\n$1000\r\n");
+        expected.extend_from_slice(&[b'o'; 1000]);
+        expected.extend_from_slice(b"\r\n$10\r\nbbbbbbbbbb\r\n");
+        assert_eq!(dst.copy_bytes_from(0, dst.len()), expected);
+    }
 }